@@ -0,0 +1,56 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use i4w_callapi::ps;
+
+// Each input grows linearly with n, so the time per byte should stay flat.
+fn inputs(n: usize) -> Vec<(&'static str, String)> {
+    vec![
+        (
+            "comma_list",
+            (0..n)
+                .map(|i| i.to_string())
+                .collect::<Vec<String>>()
+                .join(","),
+        ),
+        (
+            "comma_list_unterminated",
+            format!(
+                "@({}",
+                (0..n)
+                    .map(|i| i.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+        ),
+        (
+            "nested_arrays",
+            format!("{}1{}", "[1,".repeat(n), "]".repeat(n)),
+        ),
+        ("open_brackets", "[".repeat(n)),
+        (
+            "hashtable",
+            format!(
+                "@{{{}}}",
+                (0..n)
+                    .map(|i| format!("k{}={}", i, i))
+                    .collect::<Vec<String>>()
+                    .join(";")
+            ),
+        ),
+    ]
+}
+
+fn bench_from_str(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_str");
+    for n in [1000, 2000, 4000, 8000].iter() {
+        for (name, input) in inputs(*n) {
+            group.throughput(Throughput::Bytes(input.len() as u64));
+            group.bench_with_input(BenchmarkId::new(name, n), &input, |b, input| {
+                b.iter(|| ps::from_str(input))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_from_str);
+criterion_main!(benches);
@@ -1,10 +1,13 @@
 use clap::{value_t, App, AppSettings, Arg};
+use i4w_callapi::input::InputFormat;
+use i4w_callapi::output::OutputFormat;
 
 fn parser<'a, 'b>() -> App<'a, 'b> {
     App::new("call_api_check")
         .about("Forward check plugin invocations to icinga-powershell-restapi daemon.")
         .version("0.2.2")
         .setting(AppSettings::TrailingVarArg)
+        .after_help("The vanilla PowerShell Base command line is accepted as well, the check command and its arguments are taken from the script after -C:\n    call_api_check.exe -NoProfile -C \"try { Use-Icinga -Minimal; } catch { exit 3; }; exit Invoke-IcingaCheckCPU\" -Warning 80")
         .arg(
             Arg::with_name("host")
                 .long("host")
@@ -42,12 +45,133 @@ fn parser<'a, 'b>() -> App<'a, 'b> {
                 .required(false)
                 .help("Timeout in seconds to wait for a REST API response."),
         )
+        .arg(
+            Arg::with_name("schema")
+                .long("schema")
+                .takes_value(true)
+                .required(false)
+                .help("JSON file describing the parameters of the command, used to coerce and validate forwarded arguments."),
+        )
+        .arg(
+            Arg::with_name("credentials")
+                .long("credentials")
+                .takes_value(true)
+                .required(false)
+                .help("JSON file with named credentials, referenced as secret:cred:NAME in forwarded arguments."),
+        )
+        .arg(
+            Arg::with_name("director-compat")
+                .long("director-compat")
+                .takes_value(false)
+                .required(false)
+                .help("Normalize quirks of command lines generated by Icinga Director, like empty or unset values. More precise with --schema."),
+        )
+        .arg(
+            Arg::with_name("exact-numbers")
+                .long("exact-numbers")
+                .takes_value(false)
+                .required(false)
+                .help("Send numbers in forwarded arguments with their literal text, e.g. 1.10 stays 1.10 and big integers stay exact."),
+        )
+        .arg(
+            Arg::with_name("normalize-perfdata")
+                .long("normalize-perfdata")
+                .takes_value(false)
+                .required(false)
+                .help("Repair perfdata Icinga would reject, like unquoted labels with spaces or duplicate labels. Every fix is logged to stderr."),
+        )
+        .arg(
+            Arg::with_name("perfdata-include")
+                .long("perfdata-include")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(false)
+                .help("Only keep perfdata entries with a label matching this regular expression. Can be repeated."),
+        )
+        .arg(
+            Arg::with_name("perfdata-exclude")
+                .long("perfdata-exclude")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(false)
+                .help("Drop perfdata entries with a label matching this regular expression. Can be repeated."),
+        )
+        .arg(
+            Arg::with_name("perfdata-rename")
+                .long("perfdata-rename")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(false)
+                .help("Rename perfdata labels matching PATTERN like PATTERN=TEMPLATE, e.g. ^core_(.*)$=cpu_$1. Can be repeated, the first match applies."),
+        )
+        .arg(
+            Arg::with_name("normalize-units")
+                .long("normalize-units")
+                .takes_value(false)
+                .required(false)
+                .help("Rescale perfdata with their thresholds to the canonical unit of their family, B for bytes and s for time."),
+        )
+        .arg(
+            Arg::with_name("override-threshold")
+                .long("override-threshold")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(false)
+                .help("Evaluate the perfdata entry LABEL with other thresholds like LABEL=WARN;CRIT, using Nagios ranges like 10:20, @10:20 or ~:20. LABEL=WARN:CRIT works for plain numbers. Can be repeated."),
+        )
+        .arg(
+            Arg::with_name("map-state")
+                .long("map-state")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(false)
+                .help("Replace a state like FROM=TO, e.g. warning=ok or not-executed=critical. FROM may also be a raw exit code. Can be repeated, the first match applies."),
+        )
+        .arg(
+            Arg::with_name("escalate-state")
+                .long("escalate-state")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(false)
+                .help("Raise the state to at least STATE like PATTERN=STATE if the check output matches the regular expression. Can be repeated."),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .takes_value(true)
+                .required(false)
+                .possible_values(&["text", "json"])
+                .help("Print the check result as plugin output, or as one JSON object with the arguments, exit codes, perfdata, timing and errors. Default: text."),
+        )
+        .arg(
+            Arg::with_name("args-file")
+                .long("args-file")
+                .takes_value(true)
+                .required(false)
+                .conflicts_with("ARGS")
+                .help("JSON or YAML file with the complete argument object, instead of forwarded arguments. Use - to read from stdin."),
+        )
+        .arg(
+            Arg::with_name("args-format")
+                .long("args-format")
+                .takes_value(true)
+                .required(false)
+                .possible_values(&["json", "yaml"])
+                .requires("args-file")
+                .help("Format of --args-file. Default: guessed from the file extension, JSON for stdin."),
+        )
         .arg(
             Arg::with_name("ARGS")
                 .takes_value(true)
                 .multiple(true)
                 .allow_hyphen_values(true)
-                .help("Any number of options or paramters, forwarded to the check plugin. Positional arguments are ignored."),
+                .help("Any number of options or paramters, forwarded to the check plugin. Positional arguments require positions declared by --schema. @path splats parameters from a .psd1 or .json file."),
         )
 }
 
@@ -57,6 +181,21 @@ pub struct Cli {
     pub command: String,
     pub insecure: bool,
     pub timeout: u32,
+    pub schema: Option<String>,
+    pub credentials: Option<String>,
+    pub director_compat: bool,
+    pub exact_numbers: bool,
+    pub normalize_perfdata: bool,
+    pub perfdata_include: Vec<String>,
+    pub perfdata_exclude: Vec<String>,
+    pub perfdata_rename: Vec<String>,
+    pub normalize_units: bool,
+    pub override_thresholds: Vec<String>,
+    pub map_states: Vec<String>,
+    pub escalate_states: Vec<String>,
+    pub output: OutputFormat,
+    pub args_file: Option<String>,
+    pub args_format: Option<InputFormat>,
     pub forward_args: Vec<String>,
 }
 
@@ -68,16 +207,31 @@ impl Default for Cli {
             command: String::new(),
             insecure: false,
             timeout: 60,
+            schema: None,
+            credentials: None,
+            director_compat: false,
+            exact_numbers: false,
+            normalize_perfdata: false,
+            perfdata_include: Vec::new(),
+            perfdata_exclude: Vec::new(),
+            perfdata_rename: Vec::new(),
+            normalize_units: false,
+            override_thresholds: Vec::new(),
+            map_states: Vec::new(),
+            escalate_states: Vec::new(),
+            output: OutputFormat::Text,
+            args_file: None,
+            args_format: None,
             forward_args: Vec::new(),
         }
     }
 }
 
 impl Cli {
-    pub fn parsed() -> Self {
+    pub fn parsed_from(args: Vec<String>) -> Self {
         let app = parser();
         let mut cli = Self::default();
-        let matches = app.get_matches();
+        let matches = app.get_matches_from(args);
         if let Ok(port) = value_t!(matches, "port", u32) {
             cli.port = port;
         }
@@ -85,6 +239,29 @@ impl Cli {
             cli.command = String::from(command);
         }
         cli.insecure = matches.is_present("insecure");
+        cli.schema = matches.value_of("schema").map(String::from);
+        cli.credentials = matches.value_of("credentials").map(String::from);
+        cli.director_compat = matches.is_present("director-compat");
+        cli.exact_numbers = matches.is_present("exact-numbers");
+        cli.normalize_perfdata = matches.is_present("normalize-perfdata");
+        let values = |name: &str| -> Vec<String> {
+            matches
+                .values_of(name)
+                .map(|values| values.map(String::from).collect())
+                .unwrap_or_default()
+        };
+        cli.perfdata_include = values("perfdata-include");
+        cli.perfdata_exclude = values("perfdata-exclude");
+        cli.perfdata_rename = values("perfdata-rename");
+        cli.normalize_units = matches.is_present("normalize-units");
+        cli.override_thresholds = values("override-threshold");
+        cli.map_states = values("map-state");
+        cli.escalate_states = values("escalate-state");
+        if let Some(output) = matches.value_of("output").and_then(OutputFormat::parse) {
+            cli.output = output;
+        }
+        cli.args_file = matches.value_of("args-file").map(String::from);
+        cli.args_format = matches.value_of("args-format").and_then(InputFormat::parse);
         if let Some(forward_args) = matches.values_of("ARGS") {
             cli.forward_args = forward_args.map(|s| s.to_string()).collect();
         }
@@ -101,7 +278,7 @@ fn test_min_cli() {
         .get_matches_from_safe(vec!["call_api_check", "--command", "Invoke-Foo", "--", "1"])
         .unwrap();
     assert_eq!(matches.value_of("command").unwrap(), "Invoke-Foo");
-    assert_eq!(matches.is_present("insecure"), false);
+    assert!(!matches.is_present("insecure"));
     let trail: Vec<&str> = matches.values_of("ARGS").unwrap().collect();
     assert_eq!(trail, ["1"]);
 }
@@ -120,6 +297,30 @@ fn test_max_cli() {
             "--insecure",
             "--timeout",
             "30",
+            "--schema",
+            "Invoke-Foo.json",
+            "--credentials",
+            "credentials.json",
+            "--director-compat",
+            "--exact-numbers",
+            "--normalize-perfdata",
+            "--perfdata-include",
+            "^core_",
+            "--perfdata-exclude",
+            "_\\d+$",
+            "--perfdata-include",
+            "load",
+            "--perfdata-rename",
+            "^core_(.*)$=cpu_$1",
+            "--normalize-units",
+            "--override-threshold",
+            "core_total=@10:20;~:90",
+            "--map-state",
+            "warning=ok",
+            "--escalate-state",
+            "Stopped=critical",
+            "--output",
+            "json",
             "--",
             "-arg1",
             "1",
@@ -129,8 +330,59 @@ fn test_max_cli() {
     assert_eq!(matches.value_of("host").unwrap(), "localhost");
     assert_eq!(value_t!(matches, "port", u32).unwrap(), 5668);
     assert_eq!(matches.value_of("command").unwrap(), "Invoke-Foo");
-    assert_eq!(matches.is_present("insecure"), true);
+    assert!(matches.is_present("insecure"));
     assert_eq!(value_t!(matches, "timeout", u32).unwrap(), 30);
+    assert_eq!(matches.value_of("schema").unwrap(), "Invoke-Foo.json");
+    assert_eq!(matches.value_of("credentials").unwrap(), "credentials.json");
+    assert!(matches.is_present("director-compat"));
+    assert!(matches.is_present("exact-numbers"));
+    assert!(matches.is_present("normalize-perfdata"));
+    assert!(matches.is_present("normalize-units"));
+    assert_eq!(
+        matches.value_of("override-threshold").unwrap(),
+        "core_total=@10:20;~:90"
+    );
+    assert_eq!(matches.value_of("map-state").unwrap(), "warning=ok");
+    assert_eq!(
+        matches.value_of("escalate-state").unwrap(),
+        "Stopped=critical"
+    );
+    assert_eq!(matches.value_of("output").unwrap(), "json");
+    let includes: Vec<&str> = matches.values_of("perfdata-include").unwrap().collect();
+    assert_eq!(includes, ["^core_", "load"]);
+    assert_eq!(matches.value_of("perfdata-exclude").unwrap(), "_\\d+$");
+    assert_eq!(
+        matches.value_of("perfdata-rename").unwrap(),
+        "^core_(.*)$=cpu_$1"
+    );
     let trail: Vec<&str> = matches.values_of("ARGS").unwrap().collect();
     assert_eq!(trail, ["-arg1", "1", "-arg2"]);
 }
+
+#[test]
+fn test_args_file_cli() {
+    let matches = parser()
+        .get_matches_from_safe(vec![
+            "call_api_check",
+            "--command",
+            "Invoke-Foo",
+            "--args-file",
+            "-",
+            "--args-format",
+            "yaml",
+        ])
+        .unwrap();
+    assert_eq!(matches.value_of("args-file").unwrap(), "-");
+    assert_eq!(matches.value_of("args-format").unwrap(), "yaml");
+    assert!(parser()
+        .get_matches_from_safe(vec![
+            "call_api_check",
+            "--command",
+            "Invoke-Foo",
+            "--args-file",
+            "args.json",
+            "--",
+            "-arg1",
+        ])
+        .is_err());
+}
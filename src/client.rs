@@ -1,5 +1,4 @@
 use crate::restapiv1;
-use std::convert::TryFrom;
 use std::time::Duration;
 
 pub struct IcingaPsRestApiClient {
@@ -20,7 +19,7 @@ impl IcingaPsRestApiClient {
     pub fn checker_commnad(
         &self,
         command: &str,
-        args: &[String],
+        args: &restapiv1::CommandArguments,
     ) -> Result<restapiv1::CheckerResult, Box<dyn std::error::Error>> {
         let url = format!(
             "https://{}:{}/v1/checker?command={}",
@@ -34,7 +33,7 @@ impl IcingaPsRestApiClient {
             .build()
             .unwrap()
             .post(url)
-            .json(&restapiv1::CommandArguments::try_from(args)?)
+            .json(args)
             .send()?;
 
         let body_data = response.json::<restapiv1::CheckerResponseBody>()?;
@@ -0,0 +1,207 @@
+use crate::ps::{self, CliArgument};
+use std::fmt;
+
+// Options of powershell.exe in the vanilla "PowerShell Base" CheckCommand, which don't matter
+// when executing through the API. The second element tells whether the option takes a value.
+const POWERSHELL_OPTIONS: [(&str, bool); 5] = [
+    ("-NoProfile", false),
+    ("-NoLogo", false),
+    ("-NonInteractive", false),
+    ("-ExecutionPolicy", true),
+    ("-WindowStyle", true),
+];
+const EXECUTE_PLUGIN_COMMAND: &str = "Exit-IcingaExecutePlugin";
+
+#[derive(Debug, PartialEq)]
+pub struct ScriptError {
+    pub script: String,
+    pub reason: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to extract the check command from script '{}' ({})",
+            self.script, self.reason
+        )
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+// Lowercase -c stays the short form of --command, like powershell.exe everything else is
+// case insensitive.
+fn is_script_option(arg: &str) -> bool {
+    arg == "-C" || arg.eq_ignore_ascii_case("-Command")
+}
+
+// Turns a vanilla command line like
+//   call_api_check.exe -NoProfile -C "try { Use-Icinga -Minimal; } catch { exit 3; }; exit Invoke-IcingaCheckCPU" -Warning 80
+// into
+//   call_api_check.exe --command Invoke-IcingaCheckCPU -- -Warning 80
+// Like powershell.exe, all arguments after -C are joined to a single script. Command lines
+// without -C are returned unchanged.
+pub fn rewrite_args(args: Vec<String>) -> Result<Vec<String>, ScriptError> {
+    let script_position = match args
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .position(|arg| is_script_option(arg))
+    {
+        Some(position) => position,
+        None => return Ok(args),
+    };
+    let mut rewritten_args = Vec::new();
+    let mut options = args[..script_position].iter();
+    while let Some(arg) = options.next() {
+        match POWERSHELL_OPTIONS
+            .iter()
+            .find(|(option, _)| option.eq_ignore_ascii_case(arg))
+        {
+            Some((_, true)) => {
+                options.next();
+            }
+            Some((_, false)) => (),
+            None => rewritten_args.push(arg.clone()),
+        }
+    }
+    let (command, forward_args) = parse_script(&args[script_position + 1..].join(" "))?;
+    rewritten_args.extend(vec![String::from("--command"), command, String::from("--")]);
+    rewritten_args.extend(forward_args);
+    Ok(rewritten_args)
+}
+
+// The check command is the last statement of the script, optionally behind exit or wrapped
+// by Exit-IcingaExecutePlugin -Command.
+pub fn parse_script(script: &str) -> Result<(String, Vec<String>), ScriptError> {
+    let error = |reason: &str| ScriptError {
+        script: script.to_owned(),
+        reason: reason.to_owned(),
+    };
+    let mut words = ps::split_script(script)
+        .map_err(|e| error(&e.to_string()))?
+        .pop()
+        .ok_or_else(|| error("no statement found"))?;
+    if words
+        .first()
+        .is_some_and(|word| word.eq_ignore_ascii_case("exit"))
+    {
+        words.remove(0);
+    }
+    if words
+        .first()
+        .is_some_and(|word| word.eq_ignore_ascii_case(EXECUTE_PLUGIN_COMMAND))
+    {
+        words.remove(0);
+        let position = words
+            .iter()
+            .position(|word| is_script_option(word))
+            .ok_or_else(|| error("no -Command given to Exit-IcingaExecutePlugin"))?;
+        words.remove(position);
+        if position >= words.len() {
+            return Err(error("no -Command given to Exit-IcingaExecutePlugin"));
+        }
+        let command = words.remove(position);
+        words.insert(0, command);
+    }
+    if words.is_empty() {
+        return Err(error("no command found"));
+    }
+    let command = match ps::from_str(words.remove(0)) {
+        Ok(CliArgument::String(command)) if !command.is_empty() => command,
+        _ => return Err(error("the command name must be a string")),
+    };
+    Ok((command, words.into_iter().map(String::from).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_script, rewrite_args};
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| String::from(*arg)).collect()
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let script = "try { Use-Icinga -Minimal; } catch { Write-Output 'The Icinga PowerShell Framework is either not installed'; Write-Output 'Error:' $($_.Exception.Message); exit 3; }; exit Invoke-IcingaCheckCPU -Warning 80 -Critical '90'";
+        assert_eq!(
+            parse_script(script).unwrap(),
+            (
+                String::from("Invoke-IcingaCheckCPU"),
+                strings(&["-Warning", "80", "-Critical", "'90'"])
+            )
+        );
+        let script =
+            "Use-Icinga; Exit-IcingaExecutePlugin -Command 'Invoke-IcingaCheckCPU' -Warning 80";
+        assert_eq!(
+            parse_script(script).unwrap(),
+            (
+                String::from("Invoke-IcingaCheckCPU"),
+                strings(&["-Warning", "80"])
+            )
+        );
+        assert_eq!(
+            parse_script("Use-Icinga; exit").unwrap_err().reason,
+            "no command found"
+        );
+        assert_eq!(
+            parse_script("Exit-IcingaExecutePlugin -Warning 80")
+                .unwrap_err()
+                .reason,
+            "no -Command given to Exit-IcingaExecutePlugin"
+        );
+        let script = "<# check; -Foo #> Use-Icinga\nexit Invoke-IcingaCheckFoo -A @(1, 2) -B \"x `\" y\" -C ('a', 'b') -D @'\n'x'; y\n'@ # comment";
+        assert_eq!(
+            parse_script(script).unwrap(),
+            (
+                String::from("Invoke-IcingaCheckFoo"),
+                strings(&[
+                    "-A",
+                    "@(1, 2)",
+                    "-B",
+                    "\"x `\" y\"",
+                    "-C",
+                    "('a', 'b')",
+                    "-D",
+                    "@'\n'x'; y\n'@"
+                ])
+            )
+        );
+        assert!(parse_script("exit @(1,2)").is_err());
+    }
+
+    #[test]
+    fn test_rewrite_args() {
+        let args = strings(&["call_api_check", "-c", "Invoke-Foo", "--", "-C", "1"]);
+        assert_eq!(rewrite_args(args.clone()).unwrap(), args);
+
+        let args = strings(&[
+            "call_api_check.exe",
+            "--insecure",
+            "-NoProfile",
+            "-ExecutionPolicy",
+            "ByPass",
+            "-C",
+            "try { Use-Icinga -Minimal; } catch { exit 3; }; exit Invoke-IcingaCheckCPU",
+            "-Warning",
+            "80",
+            "-Include",
+            "@('a b', 'c')",
+        ]);
+        assert_eq!(
+            rewrite_args(args).unwrap(),
+            strings(&[
+                "call_api_check.exe",
+                "--insecure",
+                "--command",
+                "Invoke-IcingaCheckCPU",
+                "--",
+                "-Warning",
+                "80",
+                "-Include",
+                "@('a b', 'c')",
+            ])
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use crate::ps::{self, CliArgument, Number, ParameterBinderToken};
+use crate::schema::{CommandSchema, ParameterType};
+
+// What a parameter is expected to be, as far as the schema tells.
+fn parameter_type(name: &str, schema: Option<&CommandSchema>) -> Option<ParameterType> {
+    schema
+        .and_then(|schema| schema.resolve(name).ok())
+        .map(|(_, parameter)| parameter.parameter_type)
+}
+
+// Director renders unset custom vars as empty strings or as $null.
+fn is_unset(value: &str) -> bool {
+    value.trim().is_empty()
+        || value.eq_ignore_ascii_case("$null")
+        || matches!(ps::from_str(value), Ok(CliArgument::String(string)) if string.is_empty())
+}
+
+fn as_bool(value: &str) -> Option<bool> {
+    match ps::from_str(value).ok()? {
+        CliArgument::Bool(boolean) => Some(boolean),
+        CliArgument::Number(Number::PosInt(1)) => Some(true),
+        CliArgument::Number(Number::PosInt(0)) => Some(false),
+        CliArgument::String(string) if string.eq_ignore_ascii_case("true") => Some(true),
+        CliArgument::String(string) if string.eq_ignore_ascii_case("false") => Some(false),
+        _ => None,
+    }
+}
+
+// An array custom var joined to one quoted string like 'a, b' is split into its elements.
+fn split_joined_array(value: &str) -> Option<String> {
+    match ps::from_str(value).ok()? {
+        CliArgument::String(string) if string.contains(',') => {
+            Some(ps::to_string(&CliArgument::Array(
+                string
+                    .split(',')
+                    .map(|element| CliArgument::String(element.trim().to_owned()))
+                    .collect(),
+            )))
+        }
+        _ => None,
+    }
+}
+
+// Rewrites the artifacts of command lines generated by Icinga Director into what was intended,
+// before the arguments are bound. Without a schema, only quirks that can't be meant otherwise
+// are handled.
+pub fn normalize<T: AsRef<str>>(args: &[T], schema: Option<&CommandSchema>) -> Vec<String> {
+    let mut normalized_args = Vec::new();
+    let mut args = args.iter().map(|arg| arg.as_ref()).peekable();
+    while let Some(arg) = args.next() {
+        if !arg.is_parameter_name() {
+            normalized_args.push(arg.to_owned());
+            continue;
+        }
+        let parameter_type = parameter_type(&arg.as_parameter_name(), schema);
+        let value = match args.peek() {
+            Some(value) if !value.is_parameter_name() => *value,
+            // left without value, only a switch can be meant
+            _ => {
+                if parameter_type.is_none() || parameter_type == Some(ParameterType::Switch) {
+                    normalized_args.push(arg.to_owned());
+                }
+                continue;
+            }
+        };
+        match parameter_type {
+            Some(ParameterType::Switch) => {
+                // a switch never takes a value, a value it doesn't understand is left for the
+                // binder to report
+                if let Some(boolean) = as_bool(value) {
+                    args.next();
+                    if boolean {
+                        normalized_args.push(arg.to_owned());
+                    }
+                    continue;
+                }
+                if is_unset(value) {
+                    args.next();
+                    continue;
+                }
+                normalized_args.push(arg.to_owned());
+            }
+            _ if is_unset(value) => {
+                args.next();
+            }
+            Some(ParameterType::Array) => {
+                args.next();
+                normalized_args.push(arg.to_owned());
+                normalized_args.push(split_joined_array(value).unwrap_or_else(|| value.to_owned()));
+            }
+            _ => {
+                args.next();
+                normalized_args.push(arg.to_owned());
+                // boolean custom vars, unless the schema tells otherwise
+                normalized_args.push(match value {
+                    _ if parameter_type.is_some() => value.to_owned(),
+                    _ if value.eq_ignore_ascii_case("true") => String::from("$True"),
+                    _ if value.eq_ignore_ascii_case("false") => String::from("$False"),
+                    _ => value.to_owned(),
+                });
+            }
+        }
+    }
+    normalized_args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize;
+    use crate::schema::CommandSchema;
+
+    fn schema() -> CommandSchema {
+        let data = r#"{
+            "parameters": {
+                "Include": { "type": "array", "items": "string" },
+                "Warning": { "type": "int" },
+                "Status": { "type": "string" },
+                "NoPerfData": { "type": "switch" }
+            }
+        }"#;
+        serde_json::from_str(data).unwrap()
+    }
+
+    // Hand-written command lines for the known rendering of unset, empty, boolean and array
+    // custom vars, with what they're normalized to with and without schema. None of them is
+    // captured from Director, see the README.
+    const CASES: [(&[&str], &[&str], &[&str]); 10] = [
+        (
+            &["-Warning", "80", "-Status", "Running"],
+            &["-Warning", "80", "-Status", "Running"],
+            &["-Warning", "80", "-Status", "Running"],
+        ),
+        (
+            &["-Warning", "", "-Status", "Running"],
+            &["-Status", "Running"],
+            &["-Status", "Running"],
+        ),
+        (&["-Warning", "''", "-Status", "\"\""], &[], &[]),
+        (
+            &["-Warning", "$null", "-Status", "$NULL", "-Include", "a"],
+            &["-Include", "a"],
+            &["-Include", "a"],
+        ),
+        (
+            &["-Warning", "-Status", "Running"],
+            &["-Status", "Running"],
+            &["-Warning", "-Status", "Running"],
+        ),
+        (&["-Status"], &[], &["-Status"]),
+        (
+            &["-Include", "'wuauserv, bits'"],
+            &["-Include", "@(wuauserv,bits)"],
+            &["-Include", "'wuauserv, bits'"],
+        ),
+        (
+            &["-NoPerfData", "true", "-Warning", "80"],
+            &["-NoPerfData", "-Warning", "80"],
+            &["-NoPerfData", "$True", "-Warning", "80"],
+        ),
+        (
+            &["-NoPerfData", "0", "-Warning", "80"],
+            &["-Warning", "80"],
+            &["-NoPerfData", "0", "-Warning", "80"],
+        ),
+        (
+            &["-NoPerfData", "1", "-Status", "False"],
+            &["-NoPerfData", "-Status", "False"],
+            &["-NoPerfData", "1", "-Status", "$False"],
+        ),
+    ];
+
+    #[test]
+    fn test_normalize() {
+        let schema = schema();
+        for (args, with_schema, without_schema) in CASES.iter() {
+            assert_eq!(&normalize(args, Some(&schema)), with_schema, "{:?}", args);
+            assert_eq!(&normalize(args, None), without_schema, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_positional() {
+        assert_eq!(
+            normalize(&["Running", "-NoPerfData", "$False"], Some(&schema())),
+            vec!["Running"]
+        );
+    }
+}
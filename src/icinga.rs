@@ -1,3 +1,6 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ExitCode {
     Ok = 0,
     Warning = 1,
@@ -15,6 +18,27 @@ impl ExitCode {
             _ => ExitCode::Unknown,
         }
     }
+
+    pub fn parse(name: &str) -> Option<ExitCode> {
+        match name.to_lowercase().as_str() {
+            "ok" => Some(ExitCode::Ok),
+            "warning" => Some(ExitCode::Warning),
+            "critical" => Some(ExitCode::Critical),
+            "unknown" => Some(ExitCode::Unknown),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ExitCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitCode::Ok => write!(f, "OK"),
+            ExitCode::Warning => write!(f, "WARNING"),
+            ExitCode::Critical => write!(f, "CRITICAL"),
+            ExitCode::Unknown => write!(f, "UNKNOWN"),
+        }
+    }
 }
 
 pub trait IcingaTermination {
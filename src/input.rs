@@ -0,0 +1,220 @@
+use crate::ps::{CliArgument, Number, ParameterBinderToken};
+use indexmap::IndexMap;
+use std::fmt;
+use std::io::Read;
+
+// Reading from this path means reading from stdin.
+pub const STDIN_PATH: &str = "-";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputFormat {
+    Json,
+    Yaml,
+}
+
+impl InputFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.to_lowercase().as_str() {
+            "json" => Some(InputFormat::Json),
+            "yaml" | "yml" => Some(InputFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    // Guessed from the file extension, stdin and unknown extensions default to JSON.
+    pub fn from_path(path: &str) -> Self {
+        std::path::Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(InputFormat::parse)
+            .unwrap_or(InputFormat::Json)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InputError {
+    pub path: String,
+    pub reason: String,
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to read arguments from '{}' ({})",
+            self.path, self.reason
+        )
+    }
+}
+
+impl std::error::Error for InputError {}
+
+// Reads the complete argument object of a command from a file or stdin.
+pub fn read(
+    path: &str,
+    format: Option<InputFormat>,
+) -> Result<IndexMap<String, CliArgument>, InputError> {
+    let error = |reason: String| InputError {
+        path: path.to_owned(),
+        reason,
+    };
+    let content = if path == STDIN_PATH {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| error(e.to_string()))?;
+        content
+    } else {
+        std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?
+    };
+    parse(
+        &content,
+        format.unwrap_or_else(|| InputFormat::from_path(path)),
+    )
+    .map_err(error)
+}
+
+pub fn parse(content: &str, format: InputFormat) -> Result<IndexMap<String, CliArgument>, String> {
+    // serde error messages may quote the content, only report the position
+    let value: serde_json::Value = match format {
+        InputFormat::Json => serde_json::from_str(content)
+            .map_err(|e| format!("invalid JSON at line {} column {}", e.line(), e.column()))?,
+        InputFormat::Yaml => serde_yaml::from_str(content).map_err(|e| match e.location() {
+            Some(location) => format!(
+                "invalid YAML at line {} column {}",
+                location.line(),
+                location.column()
+            ),
+            None => String::from("invalid YAML"),
+        })?,
+    };
+    match from_json(value) {
+        CliArgument::Hashtable(hashtable) => check_parameter_names(hashtable),
+        _ => Err(String::from("expected an object")),
+    }
+}
+
+pub fn check_parameter_names(
+    parameters: IndexMap<String, CliArgument>,
+) -> Result<IndexMap<String, CliArgument>, String> {
+    match parameters
+        .keys()
+        .find(|param_name| !format!("-{}", param_name).is_parameter_name())
+    {
+        Some(param_name) => Err(format!("invalid parameter name '{}'", param_name)),
+        None => Ok(parameters),
+    }
+}
+
+// Values without a CliArgument counterpart, like null, are passed as raw JSON.
+fn from_json(value: serde_json::Value) -> CliArgument {
+    match value {
+        serde_json::Value::Null => CliArgument::Json(value),
+        serde_json::Value::Bool(boolean) => CliArgument::Bool(boolean),
+        serde_json::Value::Number(number) => {
+            CliArgument::Number(if let Some(unsigned) = number.as_u64() {
+                Number::PosInt(unsigned)
+            } else if let Some(signed) = number.as_i64() {
+                Number::NegInt(signed)
+            } else {
+                // floats and integers beyond 64 bit keep their literal text
+                Number::Exact(number.to_string())
+            })
+        }
+        serde_json::Value::String(string) => CliArgument::String(string),
+        serde_json::Value::Array(array) => {
+            CliArgument::Array(array.into_iter().map(from_json).collect())
+        }
+        serde_json::Value::Object(object) => CliArgument::Hashtable(
+            object
+                .into_iter()
+                .map(|(key, value)| (key, from_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, read, InputError, InputFormat};
+    use crate::ps::{CliArgument, Number};
+    use crate::test_util::TempFile;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(InputFormat::parse("YAML"), Some(InputFormat::Yaml));
+        assert_eq!(InputFormat::parse("toml"), None);
+        assert_eq!(InputFormat::from_path("C:\\args.yml"), InputFormat::Yaml);
+        assert_eq!(InputFormat::from_path("args.json"), InputFormat::Json);
+        assert_eq!(InputFormat::from_path("-"), InputFormat::Json);
+    }
+
+    #[test]
+    fn test_parse() {
+        let yaml = "Warning: 80\nCritical: -1.5\nInclude:\n  - a\n  - b\nNoPerfData: true\n";
+        let json =
+            r#"{"Warning": 80, "Critical": -1.5, "Include": ["a", "b"], "NoPerfData": true}"#;
+        let parameters = parse(yaml, InputFormat::Yaml).unwrap();
+        assert_eq!(parameters, parse(json, InputFormat::Json).unwrap());
+        assert_eq!(
+            parameters.keys().collect::<Vec<&String>>(),
+            vec!["Warning", "Critical", "Include", "NoPerfData"]
+        );
+        assert_eq!(
+            parameters.get("Critical").unwrap(),
+            &CliArgument::Number(Number::Exact("-1.5".to_owned()))
+        );
+        let parameters = parse(r#"{"Version": 1.10, "Ratio": -0.1e3}"#, InputFormat::Json).unwrap();
+        assert_eq!(
+            serde_json::to_string(&parameters).unwrap(),
+            r#"{"Version":1.10,"Ratio":-0.1e3}"#
+        );
+
+        assert_eq!(
+            parse("Warning: [80", InputFormat::Yaml).unwrap_err(),
+            "invalid YAML at line 2 column 1"
+        );
+        assert_eq!(
+            parse(r#"{"Warning": secret}"#, InputFormat::Json).unwrap_err(),
+            "invalid JSON at line 1 column 13"
+        );
+        assert_eq!(
+            parse(r#"{"Id": 18446744073709551616}"#, InputFormat::Json).unwrap()["Id"],
+            CliArgument::Number(Number::Exact("18446744073709551616".to_owned()))
+        );
+        assert_eq!(
+            parse("[1, 2]", InputFormat::Json).unwrap_err(),
+            "expected an object"
+        );
+        let parameters = parse("Warning: ~\nInclude: [a, null]\n", InputFormat::Yaml).unwrap();
+        assert_eq!(
+            parameters["Warning"],
+            CliArgument::Json(serde_json::Value::Null)
+        );
+        assert_eq!(
+            serde_json::to_string(&parameters).unwrap(),
+            r#"{"Warning":null,"Include":["a",null]}"#
+        );
+        assert_eq!(
+            parse(r#"{"1st": 1}"#, InputFormat::Json).unwrap_err(),
+            "invalid parameter name '1st'"
+        );
+    }
+
+    #[test]
+    fn test_read() {
+        let file = TempFile::new("args.yaml", "Include: a b\n");
+        let path = file.path();
+        assert_eq!(
+            read(path, None).unwrap().get("Include").unwrap(),
+            &CliArgument::String("a b".to_owned())
+        );
+        assert_eq!(
+            read(path, Some(InputFormat::Json)).unwrap_err(),
+            InputError {
+                path: path.to_owned(),
+                reason: String::from("invalid JSON at line 1 column 1")
+            }
+        );
+    }
+}
@@ -0,0 +1,17 @@
+pub mod client;
+pub mod compat;
+pub mod director;
+pub mod icinga;
+pub mod input;
+pub mod output;
+pub mod perfdata;
+pub mod ps;
+pub mod restapiv1;
+pub mod rule;
+pub mod schema;
+pub mod secret;
+pub mod splat;
+pub mod state;
+pub mod temporal;
+#[cfg(test)]
+mod test_util;
@@ -1,13 +1,157 @@
 mod cli;
-mod client;
-mod icinga;
-mod ps;
-mod restapiv1;
 
-use icinga::icinga_exit;
+use i4w_callapi::icinga::icinga_exit;
+use i4w_callapi::output::{ErrorCategory, Failure, Invocation, JsonOutput, OutputFormat};
+use i4w_callapi::{
+    client, compat, director, input, perfdata, ps, restapiv1, rule, schema, secret, splat, state,
+};
+use std::time::Instant;
 
-fn main() {
-    let app = cli::Cli::parsed();
+// Everything done to the check result after it was received.
+struct Postprocessing {
+    normalize_perfdata: bool,
+    perfdata_rules: perfdata::PerfdataRules,
+    normalize_units: bool,
+    threshold_overrides: Vec<perfdata::ThresholdOverride>,
+    state_rules: state::StateRules,
+}
+
+impl Postprocessing {
+    fn from_cli(app: &cli::Cli) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Postprocessing {
+            normalize_perfdata: app.normalize_perfdata,
+            perfdata_rules: perfdata::PerfdataRules::new(
+                &app.perfdata_include,
+                &app.perfdata_exclude,
+                &app.perfdata_rename,
+            )?,
+            normalize_units: app.normalize_units,
+            threshold_overrides: app
+                .override_thresholds
+                .iter()
+                .map(|rule| perfdata::ThresholdOverride::parse(rule))
+                .collect::<Result<Vec<perfdata::ThresholdOverride>, rule::RuleError>>()?,
+            state_rules: state::StateRules::new(&app.map_states, &app.escalate_states)?,
+        })
+    }
+
+    fn apply(
+        &self,
+        mut result: restapiv1::CheckerResult,
+    ) -> Result<restapiv1::CheckerResult, perfdata::PerfdataError> {
+        if self.normalize_perfdata {
+            let (perfdata, fixes) = result.perfdata.normalize();
+            for fix in fixes {
+                eprintln!("perfdata: {}", fix);
+            }
+            result.perfdata = perfdata;
+        }
+        if !self.perfdata_rules.is_empty() {
+            // the check result stands on its own, so perfdata that can't be parsed is kept as is
+            match result.perfdata.apply_rules(&self.perfdata_rules) {
+                Ok(perfdata) => result.perfdata = perfdata,
+                Err(e) => eprintln!("perfdata: {}, rules not applied", e),
+            }
+        }
+        if self.normalize_units {
+            match result.perfdata.normalize_units() {
+                Ok(perfdata) => result.perfdata = perfdata,
+                Err(e) => eprintln!("perfdata: {}, units not normalized", e),
+            }
+        }
+        if !self.threshold_overrides.is_empty() {
+            // without parsable perfdata, there's nothing to evaluate and the result must not change
+            result = match result.perfdata.points() {
+                Ok(points) => {
+                    for label in perfdata::unmatched_overrides(&self.threshold_overrides, &points) {
+                        eprintln!(
+                            "perfdata: no entry with label '{}', threshold not overridden",
+                            label
+                        );
+                    }
+                    result.override_thresholds(&self.threshold_overrides)?
+                }
+                Err(e) => {
+                    eprintln!("perfdata: {}, thresholds not overridden", e);
+                    result
+                }
+            };
+        }
+        if !self.state_rules.is_empty() {
+            result = result.apply_state_rules(&self.state_rules);
+        }
+        Ok(result)
+    }
+}
+
+fn prepare(
+    app: &cli::Cli,
+) -> Result<(restapiv1::CommandArguments, Postprocessing), Box<dyn std::error::Error>> {
+    let postprocessing = Postprocessing::from_cli(app)?;
+    let schema = match &app.schema {
+        Some(schema_path) => Some(schema::CommandSchema::from_file(schema_path)?),
+        None => None,
+    };
+    let options = ps::ParseOptions {
+        exact_numbers: app.exact_numbers,
+        ..Default::default()
+    };
+    let mut forward_args = splat::expand(&app.forward_args, schema.as_ref())?;
+    if app.director_compat {
+        forward_args = director::normalize(&forward_args, schema.as_ref());
+    }
+    let args = match (&app.args_file, &schema) {
+        (Some(args_file), Some(schema)) => {
+            restapiv1::CommandArguments::from(input::read(args_file, app.args_format)?)
+                .coerce(schema)?
+        }
+        (Some(args_file), None) => {
+            restapiv1::CommandArguments::from(input::read(args_file, app.args_format)?)
+        }
+        (None, Some(schema)) => {
+            restapiv1::CommandArguments::bind_with_options(&forward_args, schema, &options)?
+        }
+        (None, None) => restapiv1::CommandArguments::from_args(&forward_args, &options)?,
+    };
+    args.check_thresholds()?;
+    Ok((args, postprocessing))
+}
+
+fn run(app: &cli::Cli, invocation: &mut Invocation) -> Result<restapiv1::CheckerResult, Failure> {
+    let (args, postprocessing) =
+        prepare(app).map_err(|e| Failure::new(ErrorCategory::Arguments, e))?;
+    let secret_resolver = secret::SecretResolver::new(app.credentials.as_deref());
+    let resolved_args = args
+        .resolve_secrets(&secret_resolver)
+        .map_err(|e| Failure::new(ErrorCategory::Secret, e.into()))?;
+    invocation.arguments = Some(args);
     let restapi_client = client::IcingaPsRestApiClient::new(&app.host, app.port, app.insecure);
-    icinga_exit(restapi_client.checker_commnad(&app.command, &app.forward_args));
+    let started = Instant::now();
+    let result = restapi_client
+        .checker_commnad(&app.command, &resolved_args)
+        .map_err(Failure::from_request)?;
+    invocation.request_duration = Some(started.elapsed());
+    invocation.response = Some(result.clone());
+    postprocessing
+        .apply(result)
+        .map_err(|e| Failure::new(ErrorCategory::Response, e.into()))
+}
+
+fn main() {
+    let started = Instant::now();
+    let app = match compat::rewrite_args(std::env::args().collect()) {
+        Ok(args) => cli::Cli::parsed_from(args),
+        Err(e) => {
+            let failure = Failure::new(ErrorCategory::Arguments, e.into());
+            return icinga_exit::<restapiv1::CheckerResult, Failure>(Err(failure));
+        }
+    };
+    let mut invocation = Invocation::new(&app.command);
+    let result = run(&app, &mut invocation);
+    match app.output {
+        OutputFormat::Text => icinga_exit(result),
+        OutputFormat::Json => icinga_exit::<JsonOutput, JsonOutput>(Ok(
+            invocation.into_output(result, started.elapsed())
+        )),
+    }
 }
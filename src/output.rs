@@ -0,0 +1,331 @@
+// The --output json report. The schema is versioned by OUTPUT_VERSION, fields are only ever
+// added within a version. See "JSON output" in the README for the documented schema.
+
+use crate::icinga::{ExitCode, IcingaTermination};
+use crate::perfdata::PerfdataPoint;
+use crate::restapiv1::{CheckerResult, CommandArguments, Exitcode};
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+pub const OUTPUT_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.to_lowercase().as_str() {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+// Where an invocation failed, so consumers can tell a broken command line from a daemon that
+// can't be reached.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorCategory {
+    // command line, schema, argument or rule errors, nothing was sent
+    Arguments,
+    // a secret reference couldn't be resolved, nothing was sent
+    Secret,
+    // the daemon couldn't be reached or the request failed
+    Connection,
+    // the daemon didn't answer in time
+    Timeout,
+    // the daemon answered, but not with a usable check result
+    Response,
+}
+
+#[derive(Debug)]
+pub struct Failure {
+    pub category: ErrorCategory,
+    pub error: Box<dyn std::error::Error>,
+}
+
+impl Failure {
+    pub fn new(category: ErrorCategory, error: Box<dyn std::error::Error>) -> Self {
+        Failure { category, error }
+    }
+
+    // Errors of the API client, categorized by the underlying request error if there is one.
+    pub fn from_request(error: Box<dyn std::error::Error>) -> Self {
+        let category = match error.downcast_ref::<reqwest::Error>() {
+            Some(e) if e.is_timeout() => ErrorCategory::Timeout,
+            Some(e) if e.is_decode() || e.is_status() => ErrorCategory::Response,
+            Some(_) => ErrorCategory::Connection,
+            None => ErrorCategory::Response,
+        };
+        Failure::new(category, error)
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl IcingaTermination for Failure {
+    fn exitcode(&self) -> ExitCode {
+        ExitCode::Unknown
+    }
+
+    fn report(&self) {
+        println!("{}", self);
+        std::process::exit(self.exitcode() as i32);
+    }
+}
+
+// What is known about an invocation besides its result, filled in as it progresses.
+#[derive(Debug, Default)]
+pub struct Invocation {
+    pub command: String,
+    // as sent, but with secret references instead of the resolved secrets
+    pub arguments: Option<CommandArguments>,
+    // the check result as decoded, before perfdata and state rules were applied
+    pub response: Option<CheckerResult>,
+    pub request_duration: Option<Duration>,
+}
+
+impl Invocation {
+    pub fn new(command: &str) -> Self {
+        Invocation {
+            command: String::from(command),
+            ..Default::default()
+        }
+    }
+
+    pub fn into_output(
+        self,
+        result: Result<CheckerResult, Failure>,
+        duration: Duration,
+    ) -> JsonOutput {
+        let raw = match &self.response {
+            Some(CheckerResult {
+                exitcode: Exitcode::Executed(code),
+                ..
+            }) => Some(*code),
+            _ => None,
+        };
+        let (state, output, perfdata, perfdata_text, error) = match &result {
+            Ok(result) => (
+                result.exitcode(),
+                Some(result.checkresult.replace("\r\n", "\n")),
+                result.perfdata.points().ok(),
+                Some(result.perfdata.to_string()),
+                None,
+            ),
+            Err(failure) => (
+                failure.exitcode(),
+                None,
+                None,
+                None,
+                Some(JsonError {
+                    category: failure.category,
+                    message: failure.to_string(),
+                }),
+            ),
+        };
+        JsonOutput {
+            version: OUTPUT_VERSION,
+            command: self.command,
+            arguments: self.arguments,
+            exitcode: JsonExitCode {
+                raw,
+                mapped: state as i32,
+            },
+            state: state.to_string(),
+            output,
+            perfdata,
+            perfdata_text,
+            response: self.response,
+            timing: JsonTiming {
+                duration_ms: duration.as_millis() as u64,
+                request_ms: self
+                    .request_duration
+                    .map(|duration| duration.as_millis() as u64),
+            },
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonExitCode {
+    // as returned by the daemon, null if the check wasn't executed
+    pub raw: Option<i32>,
+    // after all rules, the exit code of call_api_check
+    pub mapped: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonTiming {
+    pub duration_ms: u64,
+    pub request_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonError {
+    pub category: ErrorCategory,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonOutput {
+    pub version: u32,
+    pub command: String,
+    pub arguments: Option<CommandArguments>,
+    pub exitcode: JsonExitCode,
+    pub state: String,
+    pub output: Option<String>,
+    // null if there is no result or its perfdata can't be parsed, see perfdata_text
+    pub perfdata: Option<Vec<PerfdataPoint>>,
+    pub perfdata_text: Option<String>,
+    pub response: Option<CheckerResult>,
+    pub timing: JsonTiming,
+    pub error: Option<JsonError>,
+}
+
+impl IcingaTermination for JsonOutput {
+    fn exitcode(&self) -> ExitCode {
+        ExitCode::from_i32(self.exitcode.mapped)
+    }
+
+    fn report(&self) {
+        match serde_json::to_string(self) {
+            Ok(json) => println!("{}", json),
+            Err(e) => println!("failed to serialize output ({})", e),
+        }
+        std::process::exit(self.exitcode() as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorCategory, Failure, Invocation, OutputFormat};
+    use crate::restapiv1::{CheckerResult, CommandArguments};
+    use std::convert::TryFrom;
+    use std::time::Duration;
+
+    fn checker_result(data: &str) -> CheckerResult {
+        serde_json::from_str(data).unwrap()
+    }
+
+    #[test]
+    fn test_output_format() {
+        assert_eq!(OutputFormat::parse("json"), Some(OutputFormat::Json));
+        assert_eq!(OutputFormat::parse("Text"), Some(OutputFormat::Text));
+        assert_eq!(OutputFormat::parse("xml"), None);
+    }
+
+    #[test]
+    fn test_json_output() {
+        let mut invocation = Invocation::new("Invoke-IcingaCheckCPU");
+        invocation.arguments = Some(
+            CommandArguments::try_from(&[String::from("-Warning"), String::from("80")][..])
+                .unwrap(),
+        );
+        invocation.response = Some(checker_result(
+            r#"{"exitcode":1,"checkresult":"[WARNING] CPU Load\r\n\\_ [WARNING] Core #0","perfdata":["'core_0'=85%;80;90;0;100"]}"#,
+        ));
+        invocation.request_duration = Some(Duration::from_millis(120));
+        let result = checker_result(
+            r#"{"exitcode":0,"checkresult":"[WARNING] CPU Load\r\n\\_ [WARNING] Core #0","perfdata":["'core_0'=85%;80;90;0;100"]}"#,
+        );
+        let output = invocation.into_output(Ok(result), Duration::from_millis(125));
+        let json: serde_json::Value = serde_json::to_value(&output).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "version": 1,
+                "command": "Invoke-IcingaCheckCPU",
+                "arguments": {"Warning": 80},
+                "exitcode": {"raw": 1, "mapped": 0},
+                "state": "OK",
+                "output": "[WARNING] CPU Load\n\\_ [WARNING] Core #0",
+                "perfdata": [{
+                    "label": "core_0",
+                    "value": 85.0,
+                    "uom": "%",
+                    "warn": {"start": 0.0, "end": 80.0, "inside": false},
+                    "crit": {"start": 0.0, "end": 90.0, "inside": false},
+                    "min": 0.0,
+                    "max": 100.0
+                }],
+                "perfdata_text": "'core_0'=85%;80;90;0;100",
+                "response": {
+                    "exitcode": 1,
+                    "checkresult": "[WARNING] CPU Load\r\n\\_ [WARNING] Core #0",
+                    "perfdata": ["'core_0'=85%;80;90;0;100"]
+                },
+                "timing": {"duration_ms": 125, "request_ms": 120},
+                "error": null
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_output_failure() {
+        let output = Invocation::new("Invoke-IcingaCheckCPU").into_output(
+            Err(Failure::new(
+                ErrorCategory::Arguments,
+                "missing value for parameter '-Warning'".into(),
+            )),
+            Duration::from_millis(2),
+        );
+        let json: serde_json::Value = serde_json::to_value(&output).unwrap();
+        assert_eq!(
+            json["exitcode"],
+            serde_json::json!({"raw": null, "mapped": 3})
+        );
+        assert_eq!(json["state"], "UNKNOWN");
+        assert_eq!(json["output"], serde_json::Value::Null);
+        assert_eq!(json["response"], serde_json::Value::Null);
+        assert_eq!(
+            json["timing"],
+            serde_json::json!({"duration_ms": 2, "request_ms": null})
+        );
+        assert_eq!(
+            json["error"],
+            serde_json::json!({
+                "category": "arguments",
+                "message": "missing value for parameter '-Warning'"
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_output_not_executed() {
+        let mut invocation = Invocation::new("Invoke-IcingaCheckFoo");
+        invocation.response = Some(checker_result(
+            r#"{"exitcode":{},"checkresult":"","perfdata":{}}"#,
+        ));
+        let result = checker_result(r#"{"exitcode":{},"checkresult":"","perfdata":{}}"#);
+        let json =
+            serde_json::to_value(invocation.into_output(Ok(result), Duration::default())).unwrap();
+        assert_eq!(
+            json["exitcode"],
+            serde_json::json!({"raw": null, "mapped": 3})
+        );
+        assert_eq!(json["perfdata"], serde_json::json!([]));
+        assert_eq!(json["perfdata_text"], "");
+        assert_eq!(
+            json["response"],
+            serde_json::json!({"exitcode": {}, "checkresult": "", "perfdata": {}})
+        );
+    }
+
+    #[test]
+    fn test_failure_category() {
+        let failure = Failure::from_request("No check result in API response.".into());
+        assert_eq!(failure.category, ErrorCategory::Response);
+        assert_eq!(failure.to_string(), "No check result in API response.");
+    }
+}
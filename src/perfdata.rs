@@ -0,0 +1,994 @@
+use crate::icinga::ExitCode;
+use crate::rule::{compile, RuleError};
+use regex::Regex;
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub struct PerfdataError {
+    pub perfdata: String,
+    pub reason: String,
+}
+
+impl fmt::Display for PerfdataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to parse perfdata '{}' ({})",
+            self.perfdata, self.reason
+        )
+    }
+}
+
+impl std::error::Error for PerfdataError {}
+
+const RULE_KIND: &str = "perfdata";
+
+// A Nagios threshold range like 10:20, ~:20 or @10:20. An open start or end is None.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Range {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+    // with @, values inside of the range alert instead of values outside
+    pub inside: bool,
+}
+
+impl Range {
+    pub fn parse(range: &str) -> Option<Range> {
+        let (inside, range) = match range.strip_prefix('@') {
+            Some(range) => (true, range),
+            None => (false, range),
+        };
+        let (start, end) = match range.split_once(':') {
+            Some(("~", end)) => (None, end),
+            Some((start, end)) => (Some(parse_number(start)?), end),
+            None => (Some(0.0), range),
+        };
+        let end = match end {
+            "" if range.contains(':') => None,
+            end => Some(parse_number(end)?),
+        };
+        if let (Some(start), Some(end)) = (start, end) {
+            if start > end {
+                return None;
+            }
+        }
+        Some(Range { start, end, inside })
+    }
+
+    // Whether the value is outside of the range, or inside for an @ range.
+    pub fn alerts(&self, value: f64) -> bool {
+        let within = self.start.is_none_or(|start| value >= start)
+            && self.end.is_none_or(|end| value <= end);
+        within == self.inside
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.inside {
+            write!(f, "@")?;
+        }
+        match (self.start, self.end) {
+            (Some(0.0), Some(end)) => write!(f, "{}", format_number(end)),
+            (Some(start), Some(end)) => {
+                write!(f, "{}:{}", format_number(start), format_number(end))
+            }
+            (Some(start), None) => write!(f, "{}:", format_number(start)),
+            (None, Some(end)) => write!(f, "~:{}", format_number(end)),
+            (None, None) => write!(f, "~:"),
+        }
+    }
+}
+
+// One entry 'label'=value[uom];[warn];[crit];[min];[max] of the perfdata.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PerfdataPoint {
+    pub label: String,
+    // None for the value U, which means the actual value couldn't be determined
+    pub value: Option<f64>,
+    pub uom: String,
+    pub warn: Option<Range>,
+    pub crit: Option<Range>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl fmt::Display for PerfdataPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if needs_quotes(&self.label) {
+            write!(f, "'{}'=", self.label.replace('\'', "''"))?;
+        } else {
+            write!(f, "{}=", self.label)?;
+        }
+        match self.value {
+            Some(value) => write!(f, "{}{}", format_number(value), self.uom)?,
+            None => write!(f, "U")?,
+        }
+        let fields = [
+            self.warn.map(|warn| warn.to_string()),
+            self.crit.map(|crit| crit.to_string()),
+            self.min.map(format_number),
+            self.max.map(format_number),
+        ];
+        // trailing empty fields are omitted
+        let len = fields
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |position| position + 1);
+        for field in &fields[..len] {
+            write!(f, ";{}", field.as_deref().unwrap_or(""))?;
+        }
+        Ok(())
+    }
+}
+
+// Units of the same family with their factor to the canonical unit, which comes first.
+const UNIT_FAMILIES: [&[(&str, f64)]; 3] = [
+    &[
+        ("B", 1.0),
+        ("KB", 1e3),
+        ("MB", 1e6),
+        ("GB", 1e9),
+        ("TB", 1e12),
+        ("PB", 1e15),
+        ("KiB", 1024.0),
+        ("MiB", 1048576.0),
+        ("GiB", 1073741824.0),
+        ("TiB", 1099511627776.0),
+        ("PiB", 1125899906842624.0),
+    ],
+    &[
+        ("s", 1.0),
+        ("ns", 1e-9),
+        ("us", 1e-6),
+        ("ms", 1e-3),
+        ("m", 60.0),
+        ("h", 3600.0),
+        ("d", 86400.0),
+    ],
+    &[("%", 1.0)],
+];
+
+impl PerfdataPoint {
+    // The state of the value by the thresholds, a value U has no state and counts as OK.
+    pub fn state(&self) -> ExitCode {
+        let alerts = |range: Option<Range>| match (range, self.value) {
+            (Some(range), Some(value)) => range.alerts(value),
+            _ => false,
+        };
+        if alerts(self.crit) {
+            ExitCode::Critical
+        } else if alerts(self.warn) {
+            ExitCode::Warning
+        } else {
+            ExitCode::Ok
+        }
+    }
+
+    // Rescales the value and all thresholds to the canonical unit of its family, i.e. B, s or
+    // %. Returns whether anything changed.
+    pub fn normalize_unit(&mut self) -> bool {
+        let (canonical, factor) = match UNIT_FAMILIES.iter().find_map(|family| {
+            family
+                .iter()
+                .find(|(unit, _)| *unit == self.uom)
+                .map(|(_, factor)| (family[0].0, *factor))
+        }) {
+            Some((canonical, factor)) if canonical != self.uom => (canonical, factor),
+            _ => return false,
+        };
+        let scale = |number: f64| number * factor;
+        let scale_range = |range: Range| Range {
+            start: range.start.map(scale),
+            end: range.end.map(scale),
+            ..range
+        };
+        self.uom = canonical.to_owned();
+        self.value = self.value.map(scale);
+        self.warn = self.warn.map(scale_range);
+        self.crit = self.crit.map(scale_range);
+        self.min = self.min.map(scale);
+        self.max = self.max.map(scale);
+        true
+    }
+}
+
+// Formats with at most 15 significant digits, so rescaling artifacts like 1100.0000000000002
+// don't show up, without exponent and trailing zeros.
+fn format_number(number: f64) -> String {
+    if number == 0.0 {
+        return String::from("0");
+    }
+    let integer_digits = number.abs().log10().floor() as i32 + 1;
+    let precision = (15 - integer_digits).max(0) as usize;
+    let formatted = format!("{:.*}", precision, number);
+    if formatted.contains('.') {
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_owned()
+    } else {
+        formatted
+    }
+}
+
+fn needs_quotes(label: &str) -> bool {
+    label.is_empty() || label.contains(|c: char| c.is_whitespace() || c == '\'' || c == '=')
+}
+
+// Plain decimal numbers only, as allowed by the plugin API.
+fn parse_number(number: &str) -> Option<f64> {
+    let digits = number.strip_prefix(['-', '+']).unwrap_or(number);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        return None;
+    }
+    number.parse().ok()
+}
+
+fn parse_optional<T>(
+    field: Option<&str>,
+    parse: fn(&str) -> Option<T>,
+    name: &str,
+) -> Result<Option<T>, String> {
+    match field {
+        None | Some("") => Ok(None),
+        Some(field) => parse(field)
+            .map(Some)
+            .ok_or_else(|| format!("invalid {} '{}'", name, field)),
+    }
+}
+
+// Parses the perfdata of a check result, a whitespace separated list of entries like
+//   'used space'=10.5GB;80;90;0;100 load=U
+// Labels with whitespace are quoted, quotes within are doubled.
+pub fn parse(perfdata: &str) -> Result<Vec<PerfdataPoint>, PerfdataError> {
+    Ok(parse_entries(perfdata)?
+        .into_iter()
+        .map(|(point, _)| point)
+        .collect())
+}
+
+// Like parse, along with the text of each entry.
+fn parse_entries(perfdata: &str) -> Result<Vec<(PerfdataPoint, &str)>, PerfdataError> {
+    let error = |reason: String| PerfdataError {
+        perfdata: perfdata.to_owned(),
+        reason,
+    };
+    let mut entries = Vec::new();
+    let mut rest = perfdata.trim_start();
+    while !rest.is_empty() {
+        let (label, after_label) = parse_label(rest).map_err(error)?;
+        let after_equals = after_label
+            .strip_prefix('=')
+            .ok_or_else(|| error(format!("missing '=' after label '{}'", label)))?;
+        let end = after_equals
+            .find(char::is_whitespace)
+            .unwrap_or(after_equals.len());
+        let point = parse_fields(label, &after_equals[..end]).map_err(error)?;
+        let text_len = rest.len() - after_equals.len() + end;
+        entries.push((point, &rest[..text_len]));
+        rest = after_equals[end..].trim_start();
+    }
+    Ok(entries)
+}
+
+// What an edit did to a perfdata entry, see edit_entries.
+enum Edit {
+    Unchanged,
+    Changed,
+    Dropped,
+}
+
+// Parses the entries of all perfdata strings and edits them one by one. Changed entries are
+// formatted again, unchanged entries keep their text.
+fn edit_entries<T: AsRef<str>>(
+    perfdatas: &[T],
+    mut edit: impl FnMut(&mut PerfdataPoint) -> Edit,
+) -> Result<Vec<String>, PerfdataError> {
+    let mut entries = Vec::new();
+    for perfdata in perfdatas {
+        for (mut point, text) in parse_entries(perfdata.as_ref())? {
+            match edit(&mut point) {
+                Edit::Unchanged => entries.push(text.to_owned()),
+                Edit::Changed => entries.push(point.to_string()),
+                Edit::Dropped => (),
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn parse_label(entry: &str) -> Result<(String, &str), String> {
+    let quoted = match entry.strip_prefix('\'') {
+        Some(quoted) => quoted,
+        None => {
+            let end = entry
+                .find(|c: char| c == '=' || c.is_whitespace())
+                .unwrap_or(entry.len());
+            if end == 0 {
+                return Err(String::from("empty label"));
+            }
+            return Ok((entry[..end].to_owned(), &entry[end..]));
+        }
+    };
+    let mut label = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((position, c)) = chars.next() {
+        if c != '\'' {
+            label.push(c);
+        } else if quoted[position + 1..].starts_with('\'') {
+            label.push('\'');
+            chars.next();
+        } else {
+            return Ok((label, &quoted[position + 1..]));
+        }
+    }
+    Err(format!("unterminated quote in label '{}'", label))
+}
+
+const FIELD_NAMES: [&str; 5] = [
+    "value",
+    "warning threshold",
+    "critical threshold",
+    "minimum",
+    "maximum",
+];
+
+fn parse_fields(label: String, fields: &str) -> Result<PerfdataPoint, String> {
+    let mut fields = fields.split(';');
+    let value_field = fields.next().unwrap_or("");
+    let (value, uom) = if value_field == "U" {
+        (None, String::new())
+    } else {
+        let end = value_field
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.')))
+            .unwrap_or(value_field.len());
+        let uom = &value_field[end..];
+        let value = parse_number(&value_field[..end])
+            .filter(|_| uom.chars().all(|c| c.is_alphabetic() || c == '%'))
+            .ok_or_else(|| format!("invalid value '{}' of '{}'", value_field, label))?;
+        (Some(value), uom.to_owned())
+    };
+    let point = PerfdataPoint {
+        warn: parse_optional(fields.next(), Range::parse, FIELD_NAMES[1])?,
+        crit: parse_optional(fields.next(), Range::parse, FIELD_NAMES[2])?,
+        min: parse_optional(fields.next(), parse_number, FIELD_NAMES[3])?,
+        max: parse_optional(fields.next(), parse_number, FIELD_NAMES[4])?,
+        label,
+        value,
+        uom,
+    };
+    if let Some(field) = fields.next() {
+        if !field.is_empty() {
+            return Err(format!("unexpected field '{}' of '{}'", field, point.label));
+        }
+    }
+    Ok(point)
+}
+
+// Repairs perfdata Icinga would reject or mangle: line breaks, unquoted labels with
+// whitespace, entries without whitespace in between, invalid characters and duplicate labels.
+// Returns the entries and a description of every fix. Entries that needed no fix keep their text.
+pub fn normalize<T: AsRef<str>>(perfdatas: &[T]) -> (Vec<String>, Vec<String>) {
+    let mut fixes = Vec::new();
+    let mut entries: Vec<(PerfdataPoint, String)> = Vec::new();
+    for perfdata in perfdatas {
+        let perfdata = perfdata.as_ref();
+        let cleaned = perfdata.replace(char::is_control, " ");
+        if cleaned != perfdata {
+            fixes.push(String::from("replaced line breaks and control characters"));
+        }
+        let mut rest = cleaned.trim_start();
+        while !rest.is_empty() {
+            let (label, quoted, after_label) = if rest.starts_with('\'') {
+                match parse_label(rest) {
+                    Ok((label, after_label)) => (label, true, after_label),
+                    Err(reason) => {
+                        fixes.push(format!("dropped '{}' ({})", rest, reason));
+                        break;
+                    }
+                }
+            } else {
+                // unquoted labels may contain whitespace, up to the =
+                let end = rest.find('=').unwrap_or(rest.len());
+                (rest[..end].trim_end().to_owned(), false, &rest[end..])
+            };
+            let after_equals = match after_label.strip_prefix('=') {
+                Some(after_equals) => after_equals,
+                None => {
+                    fixes.push(format!("dropped '{}' without value", label));
+                    rest = after_label.trim_start();
+                    continue;
+                }
+            };
+            let end = after_equals
+                .find(|c: char| c.is_whitespace() || c == '\'')
+                .unwrap_or(after_equals.len());
+            let (data, after_data) = after_equals.split_at(end);
+            let text = rest[..rest.len() - after_data.len()].to_owned();
+            rest = after_data.trim_start();
+            if after_data.starts_with('\'') {
+                fixes.push(format!("added missing whitespace after '{}'", label));
+            }
+            let mut fixed = false;
+            if !quoted && needs_quotes(&label) {
+                fixes.push(format!("quoted label '{}'", label));
+                fixed = true;
+            }
+            let point = match parse_fields(label.clone(), data) {
+                Ok(point) => point,
+                Err(reason) => match repair_fields(label, data, &mut fixes) {
+                    Some(point) => {
+                        fixed = true;
+                        point
+                    }
+                    None => {
+                        fixes.push(format!("dropped invalid entry ({})", reason));
+                        continue;
+                    }
+                },
+            };
+            let text = if fixed { point.to_string() } else { text };
+            entries.push((point, text));
+        }
+    }
+    let mut normalized: Vec<(PerfdataPoint, String)> = Vec::new();
+    for (mut point, text) in entries {
+        if !normalized
+            .iter()
+            .any(|(other, _)| other.label == point.label)
+        {
+            normalized.push((point, text));
+        } else if normalized.iter().any(|(other, _)| *other == point) {
+            fixes.push(format!("dropped duplicate '{}'", point.label));
+        } else {
+            let label = (2..)
+                .map(|n| format!("{}_{}", point.label, n))
+                .find(|label| !normalized.iter().any(|(other, _)| other.label == *label))
+                .unwrap_or_default();
+            fixes.push(format!(
+                "renamed duplicate '{}' to '{}'",
+                point.label, label
+            ));
+            point.label = label;
+            let text = point.to_string();
+            normalized.push((point, text));
+        }
+    }
+    (
+        normalized.into_iter().map(|(_, text)| text).collect(),
+        fixes,
+    )
+}
+
+// Repairs the fields of an entry one by one, a decimal comma becomes a point and any other
+// invalid field is dropped. An entry with an invalid value can't be repaired.
+fn repair_fields(label: String, data: &str, fixes: &mut Vec<String>) -> Option<PerfdataPoint> {
+    let mut repaired: Vec<String> = Vec::new();
+    for (position, field) in data.split(';').enumerate() {
+        // the fields before are valid, so this one decides
+        let parses = |field: &str| {
+            let mut fields = repaired.clone();
+            fields.push(field.to_owned());
+            parse_fields(label.clone(), &fields.join(";")).is_ok()
+        };
+        let name = FIELD_NAMES.get(position).unwrap_or(&"field");
+        let decimal = field.replace(',', ".");
+        if parses(field) {
+            repaired.push(field.to_owned());
+        } else if parses(&decimal) {
+            fixes.push(format!("replaced decimal comma in {} of '{}'", name, label));
+            repaired.push(decimal);
+        } else if position == 0 {
+            return None;
+        } else {
+            fixes.push(format!(
+                "dropped invalid {} '{}' of '{}'",
+                name, field, label
+            ));
+            repaired.push(String::new());
+        }
+    }
+    parse_fields(label, &repaired.join(";")).ok()
+}
+
+// Filters perfdata entries by label and renames them. Patterns match anywhere in the label
+// unless anchored with ^ and $.
+#[derive(Debug, Default)]
+pub struct PerfdataRules {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+    rename: Vec<(Regex, String)>,
+}
+
+impl PerfdataRules {
+    // Rename rules look like PATTERN=TEMPLATE, the template may refer to capture groups of
+    // the pattern like $1 or ${name}.
+    pub fn new<T: AsRef<str>>(
+        include: &[T],
+        exclude: &[T],
+        rename: &[T],
+    ) -> Result<Self, RuleError> {
+        let compile_all = |patterns: &[T]| {
+            patterns
+                .iter()
+                .map(|pattern| compile(RULE_KIND, pattern.as_ref(), pattern.as_ref()))
+                .collect::<Result<Vec<Regex>, RuleError>>()
+        };
+        let rename = rename
+            .iter()
+            .map(|rule| {
+                let rule = rule.as_ref();
+                match rule.rsplit_once('=') {
+                    Some((pattern, template)) => {
+                        Ok((compile(RULE_KIND, rule, pattern)?, template.to_owned()))
+                    }
+                    None => Err(RuleError::new(RULE_KIND, rule, "expected PATTERN=TEMPLATE")),
+                }
+            })
+            .collect::<Result<Vec<(Regex, String)>, RuleError>>()?;
+        Ok(PerfdataRules {
+            include: compile_all(include)?,
+            exclude: compile_all(exclude)?,
+            rename,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && self.rename.is_empty()
+    }
+
+    // Without include patterns, everything not excluded is kept.
+    fn keeps(&self, label: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|regex| regex.is_match(label)))
+            && !self.exclude.iter().any(|regex| regex.is_match(label))
+    }
+
+    // The first matching rename rule applies.
+    fn renamed(&self, label: &str) -> Option<String> {
+        self.rename
+            .iter()
+            .find(|(regex, _)| regex.is_match(label))
+            .map(|(regex, template)| regex.replace(label, template.as_str()).into_owned())
+    }
+
+    // Returns the remaining entries. Filters match the original labels, entries that aren't
+    // renamed keep their text.
+    pub fn apply<T: AsRef<str>>(&self, perfdatas: &[T]) -> Result<Vec<String>, PerfdataError> {
+        edit_entries(perfdatas, |point| {
+            if !self.keeps(&point.label) {
+                return Edit::Dropped;
+            }
+            match self.renamed(&point.label) {
+                Some(label) => {
+                    point.label = label;
+                    Edit::Changed
+                }
+                None => Edit::Unchanged,
+            }
+        })
+    }
+}
+
+// Thresholds replacing the ones of the entry with the given label, given like
+//   label=WARN;CRIT or label=WARN:CRIT
+// with Nagios ranges. As ranges contain colons, WARN:CRIT is only accepted for plain numbers
+// like 80:90. An empty range keeps the threshold of the plugin.
+#[derive(Debug, PartialEq)]
+pub struct ThresholdOverride {
+    pub label: String,
+    pub warn: Option<Range>,
+    pub crit: Option<Range>,
+}
+
+impl ThresholdOverride {
+    pub fn parse(rule: &str) -> Result<Self, RuleError> {
+        let error = |reason: &str| RuleError::new(RULE_KIND, rule, reason);
+        let (label, ranges) = rule
+            .rsplit_once('=')
+            .filter(|(label, _)| !label.is_empty())
+            .ok_or_else(|| error("expected LABEL=WARN;CRIT"))?;
+        let (warn, crit) = match ranges.split_once(';') {
+            Some(ranges) => ranges,
+            None => ranges
+                .split_once(':')
+                .filter(|(warn, crit)| parse_number(warn).is_some() && parse_number(crit).is_some())
+                .ok_or_else(|| error("ambiguous ranges, separate warning and critical with ';'"))?,
+        };
+        let parse_range = |range: &str, name: &str| match range {
+            "" => Ok(None),
+            range => Range::parse(range)
+                .map(Some)
+                .ok_or_else(|| error(&format!("invalid {} range '{}'", name, range))),
+        };
+        Ok(ThresholdOverride {
+            label: label.to_owned(),
+            warn: parse_range(warn, "warning")?,
+            crit: parse_range(crit, "critical")?,
+        })
+    }
+}
+
+// Replaces the thresholds of entries by label and evaluates all entries again. Returns the
+// entries and the worst state of all entries before and after the override. Entries without
+// override keep their text.
+pub fn override_thresholds<T: AsRef<str>>(
+    perfdatas: &[T],
+    overrides: &[ThresholdOverride],
+) -> Result<(Vec<String>, ExitCode, ExitCode), PerfdataError> {
+    let mut state_before = ExitCode::Ok;
+    let mut state_after = ExitCode::Ok;
+    let entries = edit_entries(perfdatas, |point| {
+        state_before = worst(state_before, point.state());
+        let edit = match overrides.iter().find(|rule| rule.label == point.label) {
+            Some(rule) => {
+                point.warn = rule.warn.or(point.warn);
+                point.crit = rule.crit.or(point.crit);
+                Edit::Changed
+            }
+            None => Edit::Unchanged,
+        };
+        state_after = worst(state_after, point.state());
+        edit
+    })?;
+    Ok((entries, state_before, state_after))
+}
+
+// Labels of the overrides that match none of the entries.
+pub fn unmatched_overrides<'a>(
+    overrides: &'a [ThresholdOverride],
+    points: &[PerfdataPoint],
+) -> Vec<&'a str> {
+    overrides
+        .iter()
+        .filter(|rule| !points.iter().any(|point| point.label == rule.label))
+        .map(|rule| rule.label.as_str())
+        .collect()
+}
+
+// Of OK, WARNING and CRITICAL.
+pub fn worst(state: ExitCode, other: ExitCode) -> ExitCode {
+    if other as i32 > state as i32 {
+        other
+    } else {
+        state
+    }
+}
+
+// Rescales all entries to the canonical unit of their family, see
+// PerfdataPoint::normalize_unit. Entries in canonical or unknown units keep their text.
+pub fn normalize_units<T: AsRef<str>>(perfdatas: &[T]) -> Result<Vec<String>, PerfdataError> {
+    edit_entries(perfdatas, |point| {
+        if point.normalize_unit() {
+            Edit::Changed
+        } else {
+            Edit::Unchanged
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        format_number, normalize, normalize_units, override_thresholds, parse, unmatched_overrides,
+        PerfdataPoint, PerfdataRules, Range, ThresholdOverride,
+    };
+    use crate::icinga::ExitCode;
+
+    fn point(label: &str, value: Option<f64>, uom: &str) -> PerfdataPoint {
+        PerfdataPoint {
+            label: label.to_owned(),
+            value,
+            uom: uom.to_owned(),
+            warn: None,
+            crit: None,
+            min: None,
+            max: None,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("'baz'=158;;").unwrap(),
+            vec![point("baz", Some(158.0), "")]
+        );
+        assert_eq!(
+            parse("'used space'=10.5GB;80;90;0;100 load=U").unwrap(),
+            vec![
+                PerfdataPoint {
+                    warn: Range::parse("80"),
+                    crit: Range::parse("90"),
+                    min: Some(0.0),
+                    max: Some(100.0),
+                    ..point("used space", Some(10.5), "GB")
+                },
+                point("load", None, ""),
+            ]
+        );
+        assert_eq!(
+            parse("'it''s = odd'=-1.5s;@10:20;~:5  time=0.25ms;;;;").unwrap(),
+            vec![
+                PerfdataPoint {
+                    warn: Range::parse("@10:20"),
+                    crit: Range::parse("~:5"),
+                    ..point("it's = odd", Some(-1.5), "s")
+                },
+                point("time", Some(0.25), "ms"),
+            ]
+        );
+        assert_eq!(parse("  ").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let reason = |perfdata| parse(perfdata).unwrap_err().reason;
+        assert_eq!(reason("used space=10"), "missing '=' after label 'used'");
+        assert_eq!(reason("'foo=1"), "unterminated quote in label 'foo=1'");
+        assert_eq!(reason("=1"), "empty label");
+        assert_eq!(reason("foo=abc"), "invalid value 'abc' of 'foo'");
+        assert_eq!(reason("foo=1;20:10"), "invalid warning threshold '20:10'");
+        assert_eq!(reason("foo=1;;;;;x"), "unexpected field 'x' of 'foo'");
+        assert_eq!(
+            parse("foo=1,5").unwrap_err().to_string(),
+            "failed to parse perfdata 'foo=1,5' (invalid value '1,5' of 'foo')"
+        );
+    }
+
+    #[test]
+    fn test_range() {
+        let range = |range| Range::parse(range).unwrap();
+        assert_eq!(
+            range("10"),
+            Range {
+                start: Some(0.0),
+                end: Some(10.0),
+                inside: false
+            }
+        );
+        assert!(range("10").alerts(11.0));
+        assert!(range("10").alerts(-1.0));
+        assert!(!range("10").alerts(10.0));
+        assert!(range("10:").alerts(9.0));
+        assert!(!range("~:20").alerts(-100.0));
+        assert!(range("@10:20").alerts(15.0));
+        assert!(!range("@10:20").alerts(21.0));
+        assert_eq!(Range::parse("abc"), None);
+        assert_eq!(Range::parse(""), None);
+        for text in ["10", "10:", "~:20", "@10:20", "-5:5", "~:"] {
+            assert_eq!(range(text).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        for perfdata in [
+            "'used space'=10.5GB;80;90;0;100",
+            "load=U",
+            "'it''s'=1s;;~:5",
+            "time=0.25ms;;;0",
+        ] {
+            assert_eq!(parse(perfdata).unwrap()[0].to_string(), perfdata);
+        }
+        assert_eq!(parse("'baz'=158;;").unwrap()[0].to_string(), "baz=158");
+    }
+
+    #[test]
+    fn test_normalize() {
+        let (entries, fixes) = normalize(&["'baz'=158;; 'qux'=1s", "load=U"]);
+        assert_eq!(entries, vec!["'baz'=158;;", "'qux'=1s", "load=U"]);
+        assert!(fixes.is_empty());
+
+        let (entries, fixes) = normalize(&[
+            "used space=10GB;80;90\r\n'a'=1;;'b'=2 'a'=1;; 'b'=3 cpu=1,5%;8#0 foo=abc bar",
+        ]);
+        assert_eq!(
+            entries,
+            vec![
+                "'used space'=10GB;80;90",
+                "'a'=1;;",
+                "'b'=2",
+                "b_2=3",
+                "cpu=1.5%"
+            ]
+        );
+        assert_eq!(
+            fixes,
+            vec![
+                "replaced line breaks and control characters",
+                "quoted label 'used space'",
+                "added missing whitespace after 'a'",
+                "replaced decimal comma in value of 'cpu'",
+                "dropped invalid warning threshold '8#0' of 'cpu'",
+                "dropped invalid entry (invalid value 'abc' of 'foo')",
+                "dropped 'bar' without value",
+                "dropped duplicate 'a'",
+                "renamed duplicate 'b' to 'b_2'",
+            ]
+        );
+
+        let (entries, fixes) = normalize(&["mem=5;1,5:2;x;0;100;y"]);
+        assert_eq!(entries, vec!["mem=5;1.5:2;;0;100"]);
+        assert_eq!(
+            fixes,
+            vec![
+                "replaced decimal comma in warning threshold of 'mem'",
+                "dropped invalid critical threshold 'x' of 'mem'",
+                "dropped invalid field 'y' of 'mem'",
+            ]
+        );
+
+        let (entries, fixes) = normalize(&["'open=1"]);
+        assert!(entries.is_empty());
+        assert_eq!(
+            fixes,
+            vec!["dropped ''open=1' (unterminated quote in label 'open=1')"]
+        );
+    }
+
+    #[test]
+    fn test_rules() {
+        let perfdata = [
+            "'core_0'=5%;50;90 'core_1'=4% 'core_total'=4.5%;50;90",
+            "'load'=1",
+        ];
+        let rules = |include: &[&str], exclude: &[&str], rename: &[&str]| {
+            PerfdataRules::new(include, exclude, rename).unwrap()
+        };
+        assert_eq!(
+            rules(&[], &[], &[]).apply(&perfdata).unwrap(),
+            vec![
+                "'core_0'=5%;50;90",
+                "'core_1'=4%",
+                "'core_total'=4.5%;50;90",
+                "'load'=1"
+            ]
+        );
+        assert_eq!(
+            rules(&[], &["^core_\\d+$"], &[]).apply(&perfdata).unwrap(),
+            vec!["'core_total'=4.5%;50;90", "'load'=1"]
+        );
+        assert_eq!(
+            rules(&["^core_"], &["_1$"], &["^core_(.*)$=cpu ${1}"])
+                .apply(&perfdata)
+                .unwrap(),
+            vec!["'cpu 0'=5%;50;90", "'cpu total'=4.5%;50;90"]
+        );
+        assert!(rules(&[], &[], &[]).is_empty());
+        assert!(rules(&[], &[], &["a=b"]).apply(&["foo"]).is_err());
+    }
+
+    #[test]
+    fn test_rule_errors() {
+        let empty: &[&str] = &[];
+        assert_eq!(
+            PerfdataRules::new(&["core_("], empty, empty)
+                .unwrap_err()
+                .to_string(),
+            "invalid perfdata rule 'core_(' (error: unclosed group)"
+        );
+        assert_eq!(
+            PerfdataRules::new(empty, empty, &["core"])
+                .unwrap_err()
+                .to_string(),
+            "invalid perfdata rule 'core' (expected PATTERN=TEMPLATE)"
+        );
+    }
+
+    #[test]
+    fn test_normalize_units() {
+        assert_eq!(
+            normalize_units(&[
+                "'used'=1.1KB;2:4;@3;0;8 'free'=1.5MiB 'time'=250ms;~:1000 'load'=5% 'count'=3c",
+                "'uptime'=2d 'wait'=U 'read'=3.3ms",
+            ])
+            .unwrap(),
+            vec![
+                "used=1100B;2000:4000;@3000;0;8000",
+                "free=1572864B",
+                "time=0.25s;~:1",
+                "'load'=5%",
+                "'count'=3c",
+                "uptime=172800s",
+                "'wait'=U",
+                "read=0.0033s",
+            ]
+        );
+        assert!(normalize_units(&["load"]).is_err());
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(0.0), "0");
+        assert_eq!(format_number(158.0), "158");
+        assert_eq!(format_number(-1.5), "-1.5");
+        assert_eq!(format_number(1.1 * 1e3), "1100");
+        assert_eq!(format_number(3.3 * 1e-3), "0.0033");
+        assert_eq!(format_number(1e21), "1000000000000000000000");
+        assert_eq!(format_number(3.948704), "3.948704");
+    }
+
+    #[test]
+    fn test_parse_threshold_override() {
+        let parse = |rule| ThresholdOverride::parse(rule);
+        assert_eq!(
+            parse("core_total=80:90").unwrap(),
+            ThresholdOverride {
+                label: "core_total".to_owned(),
+                warn: Range::parse("80"),
+                crit: Range::parse("90"),
+            }
+        );
+        assert_eq!(
+            parse("used space=@10:20;~:5").unwrap(),
+            ThresholdOverride {
+                label: "used space".to_owned(),
+                warn: Range::parse("@10:20"),
+                crit: Range::parse("~:5"),
+            }
+        );
+        assert_eq!(parse("load=;10").unwrap().warn, None);
+        assert_eq!(parse("load=5:5.5").unwrap().crit, Range::parse("5.5"));
+        for rule in [
+            "load=10:20:30",
+            "load=@10:20",
+            "load=10:",
+            "load=~:20",
+            "load=:20",
+            "load=10%:20%",
+            "load=80",
+        ] {
+            assert_eq!(
+                parse(rule).unwrap_err().reason,
+                "ambiguous ranges, separate warning and critical with ';'",
+                "{}",
+                rule
+            );
+        }
+        assert_eq!(parse("load=10:;~:20").unwrap().warn, Range::parse("10:"));
+        assert_eq!(
+            parse("load=x;1").unwrap_err().to_string(),
+            "invalid perfdata rule 'load=x;1' (invalid warning range 'x')"
+        );
+        assert!(parse("load").is_err());
+        assert!(parse("=1:2").is_err());
+    }
+
+    #[test]
+    fn test_override_thresholds() {
+        let perfdata = [
+            "'core_0'=85%;80;90 'core_total'=50%;80;90",
+            "'free'=5GB;@0:10",
+        ];
+        let overrides = [
+            ThresholdOverride::parse("core_total=40:60").unwrap(),
+            ThresholdOverride::parse("free=;@0:5").unwrap(),
+        ];
+        let (entries, before, after) = override_thresholds(&perfdata, &overrides).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                "'core_0'=85%;80;90",
+                "core_total=50%;40;60",
+                "free=5GB;@10;@5"
+            ]
+        );
+        assert_eq!(before, ExitCode::Warning);
+        assert_eq!(after, ExitCode::Critical);
+
+        let overrides = [ThresholdOverride::parse("core_0=90;95").unwrap()];
+        let (_, before, after) = override_thresholds(&perfdata[..1], &overrides).unwrap();
+        assert_eq!((before, after), (ExitCode::Warning, ExitCode::Ok));
+
+        let overrides = [
+            ThresholdOverride::parse("core_0=90;95").unwrap(),
+            ThresholdOverride::parse("core_1=90;95").unwrap(),
+            ThresholdOverride::parse("Free=;1").unwrap(),
+        ];
+        assert_eq!(
+            unmatched_overrides(&overrides, &parse(&perfdata.join(" ")).unwrap()),
+            vec!["core_1", "Free"]
+        );
+    }
+}
@@ -1,4 +1,8 @@
+use crate::temporal;
+use indexmap::IndexMap;
 use serde::Serialize;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -7,6 +11,9 @@ pub enum Error {
     Lexer,
     Parser,
     ParameterBinder,
+    NoPositionalParameter,
+    Expression(String),
+    Limit(String),
 }
 
 impl std::fmt::Display for Error {
@@ -15,6 +22,9 @@ impl std::fmt::Display for Error {
             Error::Lexer => write!(f, "failed to lex PowerShell syntax"),
             Error::Parser => write!(f, "failed to parse PowerShell syntax"),
             Error::ParameterBinder => write!(f, "failed to bind arguments as parameters"),
+            Error::NoPositionalParameter => write!(f, "no positional parameter left"),
+            Error::Expression(reason) => write!(f, "{}", reason),
+            Error::Limit(reason) => write!(f, "parser limit exceeded, {}", reason),
         }
     }
 }
@@ -31,17 +41,61 @@ enum Token {
     ArrayEnd,
     ArrayOpBegin,
     ArrayOpEnd,
+    HashtableBegin,
+    HashtableEnd,
+    Assign,
+    Separator,
+    Cast(String),
+    Range(Vec<i64>),
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Serialize)]
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize)]
 #[serde(untagged)]
 pub enum Number {
     PosInt(u64),
     NegInt(i64),
     Float(f64),
+    // the literal text of a JSON number, serialized as is
+    Exact(#[serde(serialize_with = "serialize_exact")] String),
+}
+
+fn serialize_exact<S: serde::Serializer>(
+    literal: &str,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    use serde::ser::Error as _;
+    literal
+        .parse::<serde_json::Number>()
+        .map_err(S::Error::custom)?
+        .serialize(serializer)
 }
 
 impl Number {
+    fn from_i64(number: i64) -> Number {
+        if number < 0 {
+            Number::NegInt(number)
+        } else {
+            Number::PosInt(number as u64)
+        }
+    }
+
+    fn as_f64(&self) -> f64 {
+        match *self {
+            Number::PosInt(unsigned) => unsigned as f64,
+            Number::NegInt(signed) => signed as f64,
+            Number::Float(float) => float,
+            Number::Exact(ref literal) => literal.parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    // The literal text if it's a valid JSON number, so it survives serialization unchanged.
+    pub fn exact(literal: &str) -> Option<Number> {
+        literal
+            .parse::<serde_json::Number>()
+            .ok()
+            .map(|_| Number::Exact(literal.to_owned()))
+    }
+
     fn parse(number: &str) -> Option<Number> {
         if let Some(first_char) = number.chars().next() {
             if first_char == '-' {
@@ -56,22 +110,331 @@ impl Number {
     }
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Serialize)]
+fn is_number_literal(literal: &str) -> bool {
+    literal.parse::<f64>().is_ok()
+}
+
+// Ranges are expanded at client side, so they must stay reasonably small.
+const MAX_RANGE_LENGTH: u64 = 10000;
+const JSON_PREFIX: &str = "json:";
+
+// Splits an unquoted range expression like 0..7 or $a..5 into its operands.
+fn range_operands(literal: &str) -> Option<(&str, &str)> {
+    let (start, end) = literal.split_once("..")?;
+    let is_operand = |operand: &str| {
+        is_number_literal(operand)
+            || operand.strip_prefix('$').is_some_and(|variable| {
+                !variable.is_empty() && variable.chars().all(|c| c.is_alphanumeric() || c == '_')
+            })
+    };
+    if is_operand(start) || is_operand(end) {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+fn range_as_array(range: &[i64]) -> Vec<CliArgument> {
+    range
+        .iter()
+        .map(|number| CliArgument::Number(Number::from_i64(*number)))
+        .collect()
+}
+
+fn expand_range(literal: &str, start: &str, end: &str) -> Result<Vec<i64>> {
+    let (start, end) = match (start.parse::<i64>(), end.parse::<i64>()) {
+        (Ok(start), Ok(end)) => (start, end),
+        _ => {
+            return Err(Error::Expression(format!(
+                "unsupported range '{}', only integer constants are allowed",
+                literal
+            )))
+        }
+    };
+    if start.abs_diff(end) >= MAX_RANGE_LENGTH {
+        return Err(Error::Expression(format!(
+            "range '{}' has more than {} elements",
+            literal, MAX_RANGE_LENGTH
+        )));
+    }
+    Ok(if start <= end {
+        (start..=end).collect()
+    } else {
+        (end..=start).rev().collect()
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 #[serde(untagged)]
 pub enum CliArgument {
     Array(Vec<CliArgument>),
     Bool(bool),
     Number(Number),
     String(String),
+    Hashtable(IndexMap<String, CliArgument>),
+    Json(serde_json::Value),
+}
+
+// Type accelerators that can prefix a value like in [string]123 to force its type.
+#[derive(Debug, PartialEq)]
+pub enum CastType {
+    String,
+    Int,
+    Long,
+    Double,
+    Bool,
+    Array,
+    DateTime,
+    TimeSpan,
+}
+
+impl CastType {
+    fn parse(type_name: &str) -> Option<CastType> {
+        match type_name.to_lowercase().as_str() {
+            "string" => Some(CastType::String),
+            "int" => Some(CastType::Int),
+            "long" => Some(CastType::Long),
+            "double" => Some(CastType::Double),
+            "bool" => Some(CastType::Bool),
+            "array" => Some(CastType::Array),
+            "datetime" => Some(CastType::DateTime),
+            "timespan" => Some(CastType::TimeSpan),
+            _ => None,
+        }
+    }
+
+    fn cast_integer(number: &Number, min: i64, max: i64) -> Result<Number> {
+        let integer = match *number {
+            Number::PosInt(unsigned) => i64::try_from(unsigned).map_err(|_| Error::Parser)?,
+            Number::NegInt(signed) => signed,
+            // PowerShell rounds half to even when casting to an integer type
+            Number::Float(float) => {
+                let rounded = float.round_ties_even();
+                if !(min as f64..=max as f64).contains(&rounded) {
+                    return Err(Error::Parser);
+                }
+                rounded as i64
+            }
+            Number::Exact(ref literal) => {
+                return Self::cast_integer(&Number::parse(literal).ok_or(Error::Parser)?, min, max)
+            }
+        };
+        if (min..=max).contains(&integer) {
+            Ok(Number::from_i64(integer))
+        } else {
+            Err(Error::Parser)
+        }
+    }
+
+    // Follows PowerShell conversion rules, e.g. [bool]'False' is true because the string isn't empty.
+    pub fn apply(&self, argument: CliArgument) -> Result<CliArgument> {
+        match self {
+            CastType::Array => match argument {
+                CliArgument::Array(array) => Ok(CliArgument::Array(array)),
+                skalar => Ok(CliArgument::Array(vec![skalar])),
+            },
+            CastType::String => match argument {
+                CliArgument::Array(_) | CliArgument::Hashtable(_) | CliArgument::Json(_) => {
+                    Err(Error::Parser)
+                }
+                CliArgument::Bool(true) => Ok(CliArgument::String("True".to_owned())),
+                CliArgument::Bool(false) => Ok(CliArgument::String("False".to_owned())),
+                CliArgument::Number(Number::PosInt(unsigned)) => {
+                    Ok(CliArgument::String(unsigned.to_string()))
+                }
+                CliArgument::Number(Number::NegInt(signed)) => {
+                    Ok(CliArgument::String(signed.to_string()))
+                }
+                CliArgument::Number(Number::Float(float)) => {
+                    Ok(CliArgument::String(float.to_string()))
+                }
+                CliArgument::Number(Number::Exact(literal)) => Ok(CliArgument::String(literal)),
+                CliArgument::String(string) => Ok(CliArgument::String(string)),
+            },
+            CastType::Bool => match argument {
+                CliArgument::Array(_) | CliArgument::Json(_) => Err(Error::Parser),
+                CliArgument::Hashtable(_) => Ok(CliArgument::Bool(true)),
+                CliArgument::Bool(boolean) => Ok(CliArgument::Bool(boolean)),
+                CliArgument::Number(number) => Ok(CliArgument::Bool(number.as_f64() != 0.0)),
+                CliArgument::String(string) => Ok(CliArgument::Bool(!string.is_empty())),
+            },
+            CastType::Int | CastType::Long | CastType::Double => {
+                let number = match argument {
+                    CliArgument::Array(_) | CliArgument::Hashtable(_) | CliArgument::Json(_) => {
+                        return Err(Error::Parser)
+                    }
+                    CliArgument::Bool(boolean) => Number::PosInt(boolean as u64),
+                    CliArgument::Number(number) => number,
+                    CliArgument::String(string) => {
+                        Number::parse(string.trim()).ok_or(Error::Parser)?
+                    }
+                };
+                Ok(CliArgument::Number(match self {
+                    CastType::Int => Self::cast_integer(&number, i32::MIN.into(), i32::MAX.into())?,
+                    CastType::Long => Self::cast_integer(&number, i64::MIN, i64::MAX)?,
+                    _ => Number::Float(number.as_f64()),
+                }))
+            }
+            // normalized to a locale independent string, which PowerShell converts back on binding
+            CastType::DateTime | CastType::TimeSpan => {
+                let text = match CastType::String.apply(argument)? {
+                    CliArgument::String(text) => text,
+                    _ => return Err(Error::Parser),
+                };
+                let normalized = match self {
+                    CastType::DateTime => temporal::normalize_datetime(&text),
+                    _ => temporal::normalize_timespan(&text),
+                };
+                normalized.map(CliArgument::String).ok_or(Error::Parser)
+            }
+        }
+    }
+}
+
+// Bounds the resources spent on a single argument, so pathological input fails with an error
+// instead of exhausting the stack or taking forever.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    // nested arrays, hashtables and casts
+    pub max_depth: usize,
+    pub max_tokens: usize,
+    // in bytes
+    pub max_length: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: 32,
+            max_tokens: 65536,
+            max_length: 1048576,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ParseOptions {
+    pub limits: Limits,
+    // numbers keep their literal text instead of being converted to u64, i64 or f64
+    pub exact_numbers: bool,
+    // numbers are strings of their literal text, like with a [string] cast
+    pub numbers_as_strings: bool,
 }
 
 pub fn from_str(input: &str) -> Result<CliArgument> {
+    from_str_with_options(input, &ParseOptions::default())
+}
+
+pub fn from_str_with_options(input: &str, options: &ParseOptions) -> Result<CliArgument> {
+    let limits = &options.limits;
+    if input.len() > limits.max_length {
+        return Err(Error::Limit(format!(
+            "argument is longer than {} bytes",
+            limits.max_length
+        )));
+    }
+    if let Some(json) = input.strip_prefix(JSON_PREFIX) {
+        return from_json(json);
+    }
     let lexer = Lexer::from_str(input);
     let tokens = lexer.lex()?;
-    let mut parser = Parser { input: &tokens };
+    if tokens.len() > limits.max_tokens {
+        return Err(Error::Limit(format!(
+            "argument has more than {} tokens",
+            limits.max_tokens
+        )));
+    }
+    let mut parser = Parser::new(&tokens).with_options(*options);
     parser.parse_argument()
 }
 
+// Splits a script into its statements, each as the top level words as written. Statements end
+// at ';' and line breaks outside of quotes, parantheses and script blocks, comments are dropped.
+pub fn split_script(script: &str) -> Result<Vec<Vec<&str>>> {
+    let mut lexer = Lexer::from_str(script);
+    lexer.script = Some(ScriptSplit::default());
+    let mut split = lexer.split()?;
+    split.end_statement(script.len());
+    Ok(split
+        .statements
+        .into_iter()
+        .map(|words| {
+            words
+                .into_iter()
+                .map(|(start, end)| &script[start..end])
+                .collect()
+        })
+        .collect())
+}
+
+// Inverse of from_str, renders an argument as a single command line token.
+pub fn to_string(argument: &CliArgument) -> String {
+    match argument {
+        CliArgument::Array(array) => format!(
+            "@({})",
+            array
+                .iter()
+                .map(to_string)
+                .collect::<Vec<String>>()
+                .join(",")
+        ),
+        CliArgument::Bool(true) => "$True".to_owned(),
+        CliArgument::Bool(false) => "$False".to_owned(),
+        CliArgument::Number(Number::PosInt(unsigned)) => unsigned.to_string(),
+        CliArgument::Number(Number::NegInt(signed)) => signed.to_string(),
+        // Debug formatting keeps the fraction of integral floats, so 1.0 isn't read back as 1
+        CliArgument::Number(Number::Float(float)) if float.is_finite() => format!("{:?}", float),
+        CliArgument::Number(Number::Float(float)) => format!("[double]'{:?}'", float),
+        CliArgument::Number(Number::Exact(literal)) => literal.clone(),
+        // the cast keeps the literal text of a number token
+        CliArgument::String(string) if is_number_literal(string) => {
+            format!("[string]{}", quote(string))
+        }
+        CliArgument::String(string) => quote(string),
+        CliArgument::Hashtable(hashtable) => format!(
+            "@{{{}}}",
+            hashtable
+                .iter()
+                .map(|(key, value)| format!("{}={}", quote(key), to_string(value)))
+                .collect::<Vec<String>>()
+                .join(";")
+        ),
+        CliArgument::Json(value) => {
+            format!("{}'{}'", JSON_PREFIX, value.to_string().replace('\'', "''"))
+        }
+    }
+}
+
+// Raw JSON after the json: prefix, optionally in single quotes like json:'{"a":[1,null]}'.
+fn from_json(json: &str) -> Result<CliArgument> {
+    let json = match json
+        .strip_prefix('\'')
+        .and_then(|quoted| quoted.strip_suffix('\''))
+    {
+        Some(quoted) => quoted.replace("''", "'"),
+        None => json.to_owned(),
+    };
+    serde_json::from_str(&json)
+        .map(CliArgument::Json)
+        .map_err(|e| Error::Expression(format!("invalid JSON argument ({})", e)))
+}
+
+// Quotes only if the string would have special meaning otherwise.
+fn quote(string: &str) -> String {
+    let is_bare = !string.is_empty()
+        && !string.starts_with('-')
+        && string.chars().all(|c| {
+            c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ':' | '/' | '\\' | '*' | '%' | '+')
+        });
+    if is_bare && range_operands(string).is_none() && !string.starts_with(JSON_PREFIX) {
+        string.to_owned()
+    } else if !string.contains('\'') {
+        format!("'{}'", string)
+    } else {
+        format!("\"{}\"", string.replace('`', "``").replace('"', "`\""))
+    }
+}
+
 #[derive(Debug, PartialEq)]
 enum LexerState {
     Control,
@@ -81,26 +444,78 @@ enum LexerState {
     ParanthesesCmd,
 }
 
+// Top level word and statement boundaries, only tracked by split_script.
+#[derive(Default)]
+struct ScriptSplit {
+    block_depth: usize,
+    word_start: Option<usize>,
+    words: Vec<(usize, usize)>,
+    statements: Vec<Vec<(usize, usize)>>,
+}
+
+impl ScriptSplit {
+    fn end_word(&mut self, position: usize) {
+        if let Some(start) = self.word_start.take() {
+            self.words.push((start, position));
+        }
+    }
+
+    fn end_statement(&mut self, position: usize) {
+        self.end_word(position);
+        if !self.words.is_empty() {
+            self.statements.push(std::mem::take(&mut self.words));
+        }
+    }
+}
+
 struct Lexer<'a> {
     input: &'a str,
+    input_len: usize,
     tokens: Vec<Token>,
     state: LexerState,
     escaping: bool,
+    parantheses_depth: usize,
+    parantheses_quote: Option<char>,
+    hashtable_depth: usize,
+    array_op_depth: usize,
+    after_whitespace: bool,
+    quoted: bool,
+    verbatim: bool,
     buf: String,
+    script: Option<ScriptSplit>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn from_str(input: &'a str) -> Self {
         Lexer {
             input,
+            input_len: input.len(),
             tokens: Vec::new(),
             state: LexerState::Control,
             escaping: false,
+            parantheses_depth: 0,
+            parantheses_quote: None,
+            hashtable_depth: 0,
+            array_op_depth: 0,
+            after_whitespace: false,
+            quoted: false,
+            verbatim: false,
             buf: String::new(),
+            script: None,
         }
     }
 
     pub fn lex(mut self) -> Result<Vec<Token>> {
+        self.run()?;
+        Ok(self.tokens)
+    }
+
+    fn split(mut self) -> Result<ScriptSplit> {
+        self.run()?;
+        Ok(self.script.unwrap_or_default())
+    }
+
+    fn run(&mut self) -> Result<()> {
         while !self.input.is_empty() {
             match self.state {
                 LexerState::Control => self.scan_control(),
@@ -110,36 +525,96 @@ impl<'a> Lexer<'a> {
                 LexerState::ParanthesesCmd => self.scan_parantheses_cmd(),
             }?
         }
-        self.store_buf_as_token();
-        Ok(self.tokens)
+        self.store_buf_as_token()
+    }
+
+    fn position(&self) -> usize {
+        self.input_len - self.input.len()
+    }
+
+    fn is_top_level(&self) -> bool {
+        self.hashtable_depth == 0
+            && self.array_op_depth == 0
+            && self.script.as_ref().map_or(0, |split| split.block_depth) == 0
     }
 
     fn scan_control(&mut self) -> Result<()> {
         if let Some(peeked_char) = self.input.chars().next() {
-            self.eat(1);
+            let position = self.position();
+            self.eat(peeked_char.len_utf8());
+            // like in PowerShell, # only starts a comment at the beginning of a word
+            let at_word_start = self.buf.is_empty() || self.after_whitespace;
+            self.after_whitespace = false;
+            let top_level = !self.escaping && self.is_top_level();
+            let is_comment =
+                peeked_char == '#' || (peeked_char == '<' && self.input.starts_with('#'));
+            if let Some(split) = self.script.as_mut() {
+                if top_level && matches!(peeked_char, ';' | '\n') {
+                    split.end_statement(position);
+                } else if top_level && matches!(peeked_char, ' ' | '\t' | '\r') {
+                    split.end_word(position);
+                } else if split.word_start.is_none() && !(is_comment && at_word_start) {
+                    split.word_start = Some(position);
+                }
+                if !self.escaping && self.hashtable_depth == 0 {
+                    match peeked_char {
+                        '{' => split.block_depth += 1,
+                        '}' => split.block_depth = split.block_depth.saturating_sub(1),
+                        _ => (),
+                    }
+                }
+            }
             if self.escaping {
                 self.buf.push(peeked_char);
                 self.escaping = false;
             } else if peeked_char == '"' {
+                self.quoted = true;
                 self.state = LexerState::DoubleQuote;
             } else if peeked_char == '\'' {
+                self.quoted = true;
+                self.verbatim = true;
                 self.state = LexerState::SingleQuote;
-            } else if matches!(peeked_char, ' ' | '\t' | '\r') {
+            } else if peeked_char == '#' && at_word_start {
+                let comment_end = self.input.find('\n').unwrap_or(self.input.len());
+                self.eat(comment_end);
+            } else if peeked_char == '<' && at_word_start && self.input.starts_with('#') {
+                let comment_end = self.input[1..].find("#>").ok_or(Error::Lexer)?;
+                self.eat(comment_end + 3);
+            } else if (self.hashtable_depth > 0 || self.array_op_depth > 0)
+                && matches!(peeked_char, ';' | '\n')
+            {
+                self.store_buf_as_token()?;
+                self.tokens.push(Token::Separator);
+            } else if matches!(peeked_char, ' ' | '\t' | '\r' | '\n') {
+                self.after_whitespace = true;
+            } else if self.hashtable_depth > 0 && peeked_char == '=' {
+                self.store_buf_as_token()?;
+                self.tokens.push(Token::Assign);
+            } else if self.hashtable_depth > 0 && peeked_char == '}' {
+                self.store_buf_as_token()?;
+                self.tokens.push(Token::HashtableEnd);
+                self.hashtable_depth -= 1;
             } else if peeked_char == '[' {
-                self.tokens.push(Token::ArrayBegin);
+                if let Some(type_name) = self.scan_cast() {
+                    self.tokens.push(Token::Cast(type_name));
+                } else {
+                    self.tokens.push(Token::ArrayBegin);
+                }
             } else if peeked_char == ']' {
-                self.store_buf_as_token();
+                self.store_buf_as_token()?;
                 self.tokens.push(Token::ArrayEnd);
             } else if peeked_char == '(' {
                 self.buf.push(peeked_char);
+                self.parantheses_depth = 1;
                 self.state = LexerState::ParanthesesCmd;
             } else if peeked_char == ')' {
-                self.store_buf_as_token();
+                self.store_buf_as_token()?;
                 self.tokens.push(Token::ArrayOpEnd);
+                self.array_op_depth = self.array_op_depth.saturating_sub(1);
             } else if peeked_char == '`' {
                 self.escaping = true;
             } else if peeked_char == ',' {
-                self.store_buf_as_token();
+                self.store_buf_as_token()?;
                 self.tokens.push(Token::Comma);
             } else if peeked_char == '@' {
                 self.state = LexerState::MaybeArrayOp;
@@ -152,9 +627,9 @@ impl<'a> Lexer<'a> {
 
     fn scan_singlequote(&mut self) -> Result<()> {
         let peeked_char = self.input.chars().next().ok_or(Error::Lexer)?;
-        self.eat(1);
+        self.eat(peeked_char.len_utf8());
         if peeked_char == '\'' {
-            self.store_buf_as_token();
+            self.store_buf_as_token()?;
             self.state = LexerState::Control;
         } else {
             self.buf.push(peeked_char);
@@ -164,14 +639,14 @@ impl<'a> Lexer<'a> {
 
     fn scan_doublequote(&mut self) -> Result<()> {
         let peeked_char = self.input.chars().next().ok_or(Error::Lexer)?;
-        self.eat(1);
+        self.eat(peeked_char.len_utf8());
         if self.escaping {
             self.buf.push(peeked_char);
             self.escaping = false;
         } else if peeked_char == '`' {
             self.escaping = true;
         } else if peeked_char == '"' {
-            self.store_buf_as_token();
+            self.store_buf_as_token()?;
             self.state = LexerState::Control;
         } else {
             self.buf.push(peeked_char);
@@ -179,22 +654,73 @@ impl<'a> Lexer<'a> {
         Ok(())
     }
 
+    // Parenthesized expressions and $( ... ) subexpressions are evaluated at API side, so
+    // they're kept verbatim. Only nesting and quoting is tracked to find the closing ')'.
     fn scan_parantheses_cmd(&mut self) -> Result<()> {
         let peeked_char = self.input.chars().next().ok_or(Error::Lexer)?;
-        self.eat(1);
+        self.eat(peeked_char.len_utf8());
         self.buf.push(peeked_char);
-        if peeked_char == ')' {
-            self.store_buf_as_token();
-            self.state = LexerState::Control;
+        if self.escaping {
+            self.escaping = false;
+        } else if peeked_char == '`' && self.parantheses_quote != Some('\'') {
+            self.escaping = true;
+        } else if let Some(quote) = self.parantheses_quote {
+            if peeked_char == quote {
+                self.parantheses_quote = None;
+            }
+        } else if matches!(peeked_char, '\'' | '"') {
+            self.parantheses_quote = Some(peeked_char);
+        } else if peeked_char == '(' {
+            self.parantheses_depth += 1;
+        } else if peeked_char == ')' {
+            self.parantheses_depth -= 1;
+            if self.parantheses_depth == 0 {
+                self.store_buf_as_token()?;
+                self.state = LexerState::Control;
+            }
         }
         Ok(())
     }
 
+    // A [name] directly followed by a value is a type cast like in PowerShell. Anything else
+    // starting with '[' remains an array, so e.g. [foo] and [foo],[bar] are still arrays.
+    fn scan_cast(&mut self) -> Option<String> {
+        // stops at the first character that can't be part of a type name, so a long run of
+        // [ isn't scanned again and again
+        let cast_end = self
+            .input
+            .find(|c: char| !(c.is_alphanumeric() || c == '.'))?;
+        let type_name = &self.input[..cast_end];
+        if !type_name.starts_with(char::is_alphabetic) || !self.input[cast_end..].starts_with(']') {
+            return None;
+        }
+        let remainder = self.input[cast_end + 1..].trim_start_matches([' ', '\t', '\r']);
+        match remainder.chars().next() {
+            None | Some(',') | Some(']') | Some(')') => None,
+            _ => {
+                let type_name = type_name.to_owned();
+                self.eat(cast_end + 1);
+                Some(type_name)
+            }
+        }
+    }
+
     fn scan_maybearrayop(&mut self) -> Result<()> {
         let peeked_char = self.input.chars().next().ok_or(Error::Lexer)?;
-        self.eat(1);
+        self.eat(peeked_char.len_utf8());
         if peeked_char == '(' {
             self.tokens.push(Token::ArrayOpBegin);
+            self.array_op_depth += 1;
+        } else if matches!(peeked_char, '\'' | '"')
+            && self
+                .input
+                .trim_start_matches([' ', '\t', '\r'])
+                .starts_with('\n')
+        {
+            self.scan_herestring(peeked_char)?;
+        } else if peeked_char == '{' {
+            self.tokens.push(Token::HashtableBegin);
+            self.hashtable_depth += 1;
         } else {
             self.buf.push('@');
             self.buf.push(peeked_char);
@@ -203,23 +729,51 @@ impl<'a> Lexer<'a> {
         Ok(())
     }
 
+    // @' ... '@ and @" ... "@ here-strings, kept as written from the line after the opening quote
+    // up to the line starting with the closing quote
+    fn scan_herestring(&mut self, quote: char) -> Result<()> {
+        let line_end = self.input.find('\n').ok_or(Error::Lexer)?;
+        let terminator = format!("\n{}@", quote);
+        let content_end = line_end
+            + self.input[line_end..]
+                .find(&terminator)
+                .ok_or(Error::Lexer)?;
+        let content = match self.input.get(line_end + 1..content_end) {
+            Some(content) => content.strip_suffix('\r').unwrap_or(content),
+            None => "",
+        };
+        self.buf.push_str(content);
+        self.quoted = true;
+        self.verbatim = quote == '\'';
+        self.eat(content_end + terminator.len());
+        self.store_buf_as_token()
+    }
+
     fn is_number(&self) -> bool {
-        self.buf.parse::<f64>().is_ok()
+        is_number_literal(&self.buf)
     }
 
+    // single quotes disable special meaning, so '$True' stays a string
     fn is_bool(&self) -> Option<bool> {
-        if self.buf == "$True" {
+        if self.verbatim {
+            None
+        } else if self.buf.eq_ignore_ascii_case("$True") {
             Some(true)
-        } else if self.buf == "$False" {
+        } else if self.buf.eq_ignore_ascii_case("$False") {
             Some(false)
         } else {
             None
         }
     }
 
-    fn store_buf_as_token(&mut self) {
-        if !self.buf.is_empty() {
-            if self.is_number() {
+    fn store_buf_as_token(&mut self) -> Result<()> {
+        // quotes make a token even if empty, like ''
+        if !self.buf.is_empty() || self.quoted {
+            if let Some((start, end)) = range_operands(&self.buf).filter(|_| !self.quoted) {
+                let range = expand_range(&self.buf, start, end)?;
+                self.buf.clear();
+                self.tokens.push(Token::Range(range));
+            } else if self.is_number() {
                 self.tokens
                     .push(Token::Number(std::mem::take(&mut self.buf)));
             } else if let Some(bool_value) = self.is_bool() {
@@ -230,6 +784,9 @@ impl<'a> Lexer<'a> {
                     .push(Token::String(std::mem::take(&mut self.buf)));
             }
         }
+        self.quoted = false;
+        self.verbatim = false;
+        Ok(())
     }
 
     fn eat(&mut self, num: usize) {
@@ -239,150 +796,276 @@ impl<'a> Lexer<'a> {
 
 pub struct Parser<'a> {
     input: &'a [Token],
+    depth: usize,
+    options: ParseOptions,
+}
+
+// Only Error::Parser means the rule doesn't match, any other error ends parsing.
+fn optional<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(parsed) => Ok(Some(parsed)),
+        Err(Error::Parser) => Ok(None),
+        Err(e) => Err(e),
+    }
 }
 
 impl<'a> Parser<'a> {
-    // argument : array
-    //          | sequence_by_comma_op
-    //          | SKALAR'''
+    fn new(input: &'a [Token]) -> Self {
+        Parser {
+            input,
+            depth: 0,
+            options: ParseOptions::default(),
+        }
+    }
+
+    fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // argument : sequence_by_comma_op
+    //          | element
+    // sequence_by_comma_op : comma_op
+    //                      | comma_op sequence
+    // comma_op : sequence_element COMMA
+    // Both alternatives start with a sequence_element, the following COMMA decides without
+    // backtracking.
     pub fn parse_argument(&mut self) -> Result<CliArgument> {
-        self.parse_sequence_by_comma_op().or_else(|_| {
-            self.parse_array()
-                .or_else(|_| self.parse_skalar().or(Err(Error::Parser)))
-        })
+        let is_range = matches!(self.input.first(), Some(Token::Range(_)));
+        let mut sequence = self.parse_sequence_element()?;
+        if self.parse_newtype_token(Token::Comma).is_ok() {
+            self.skip_separators();
+            if let Some(mut tail) = optional(self.parse_sequence())? {
+                sequence.append(&mut tail);
+            }
+            Ok(CliArgument::Array(sequence))
+        } else if is_range {
+            Ok(CliArgument::Array(sequence))
+        } else {
+            sequence.pop().ok_or(Error::Parser)
+        }
     }
 
     // array : ARRAY_BEGIN sequence ARRAY_END
-    //       | ARRAY_OP sequence PARANTHESES_CLOSE
+    //       | ARRAY_OP statements PARANTHESES_CLOSE
     //       | ARRAY_OP PARANTHESES_CLOSE
     //       | ARRAY_BEGIN ARRAY_END
     fn parse_array(&mut self) -> Result<CliArgument> {
         let backtrack = self.input;
-        /*if self.parse_array_empty().is_ok() {
-            return Ok(CliArgument::Array(Vec::new()));
-        }*/
-        if self.parse_newtype_token(Token::ArrayBegin).is_ok() {
-            let mut sequence_by_array = Vec::new();
-            if let Ok(mut sequence) = self.parse_sequence() {
-                sequence_by_array.append(&mut sequence);
-            }
-            if self.parse_newtype_token(Token::ArrayEnd).is_ok() {
-                return Ok(CliArgument::Array(sequence_by_array));
-            }
-        } else if self.parse_newtype_token(Token::ArrayOpBegin).is_ok() {
-            let mut sequence_by_array = Vec::new();
-            if let Ok(mut sequence) = self.parse_sequence() {
-                sequence_by_array.append(&mut sequence);
-            }
-            if self.parse_newtype_token(Token::ArrayOpEnd).is_ok() {
-                return Ok(CliArgument::Array(sequence_by_array));
-            }
+        let array_end = match self.input.first() {
+            Some(Token::ArrayBegin) => Token::ArrayEnd,
+            Some(Token::ArrayOpBegin) => Token::ArrayOpEnd,
+            _ => return Err(Error::Parser),
+        };
+        self.input = &self.input[1..];
+        self.enter()?;
+        let sequence = if array_end == Token::ArrayOpEnd {
+            self.parse_statements()
+        } else {
+            optional(self.parse_sequence()).map(Option::unwrap_or_default)
+        };
+        self.depth -= 1;
+        let sequence = sequence?;
+        if self.parse_newtype_token(array_end).is_ok() {
+            return Ok(CliArgument::Array(sequence));
         }
         self.input = backtrack;
         Err(Error::Parser)
     }
 
-    // comma_op : element COMMA
-    fn parse_comma_op(&mut self) -> Result<Vec<CliArgument>> {
-        let backtrack = self.input;
-        if let Ok(element) = self.parse_element() {
-            if self.parse_newtype_token(Token::Comma).is_ok() {
-                return Ok(vec![element]);
+    // statements : SEPARATOR* (sequence (SEPARATOR+ sequence)*)? SEPARATOR*
+    // Like @() in PowerShell, the elements of all statements are collected into one array.
+    fn parse_statements(&mut self) -> Result<Vec<CliArgument>> {
+        let mut statements = Vec::new();
+        loop {
+            self.skip_separators();
+            match optional(self.parse_sequence())? {
+                Some(mut sequence) => statements.append(&mut sequence),
+                None => return Ok(statements),
+            }
+            if self.input.first() != Some(&Token::Separator) {
+                return Ok(statements);
             }
         }
-        self.input = backtrack;
-        Err(Error::Parser)
     }
 
-    // sequence_by_comma_op : comma_op
-    //                      | comma_op sequence
-    fn parse_sequence_by_comma_op(&mut self) -> Result<CliArgument> {
-        let backtrack = self.input;
-        if let Ok(mut sequence_by_comma_op) = self.parse_comma_op() {
-            if let Ok(mut sequence) = self.parse_sequence() {
-                sequence_by_comma_op.append(&mut sequence);
+    // sequence : sequence_element
+    //          | sequence_element COMMA sequence
+    // A line break after COMMA continues the sequence.
+    fn parse_sequence(&mut self) -> Result<Vec<CliArgument>> {
+        let mut sequence = self.parse_sequence_element()?;
+        while self.parse_newtype_token(Token::Comma).is_ok() {
+            self.skip_separators();
+            match optional(self.parse_sequence_element())? {
+                Some(mut elements) => sequence.append(&mut elements),
+                None => break,
             }
-            Ok(CliArgument::Array(sequence_by_comma_op))
-        } else {
-            self.input = backtrack;
-            Err(Error::Parser)
         }
+        Ok(sequence)
     }
 
-    // sequence : element
-    //          | element COMMA sequence"""
-    fn parse_sequence(&mut self) -> Result<Vec<CliArgument>> {
-        let backtrack = self.input;
-        if let Ok(element) = self.parse_element() {
-            let mut sequence = vec![element];
-            loop {
-                if self.parse_newtype_token(Token::Comma).is_err() {
-                    break;
-                }
-                if let Ok(element) = self.parse_element() {
-                    sequence.push(element);
-                } else {
-                    break;
-                }
-            }
-            Ok(sequence)
-        } else {
-            self.input = backtrack;
-            Err(Error::Parser)
+    // sequence_element : RANGE
+    //                  | element
+    // Ranges are spliced into the enclosing sequence, so 0..2,5 is [0,1,2,5].
+    fn parse_sequence_element(&mut self) -> Result<Vec<CliArgument>> {
+        if let Some(Token::Range(range)) = self.input.first() {
+            self.input = &self.input[1..];
+            return Ok(range_as_array(range));
         }
+        self.parse_element().map(|element| vec![element])
     }
 
-    // element : skalar
+    // element : cast
+    //         | skalar
     //         | array
+    //         | hashtable
+    //         | RANGE
+    // The first token decides which rule applies.
     fn parse_element(&mut self) -> Result<CliArgument> {
+        match self.input.first() {
+            Some(Token::Range(range)) => {
+                self.input = &self.input[1..];
+                Ok(CliArgument::Array(range_as_array(range)))
+            }
+            Some(Token::Cast(_)) => self.parse_cast(),
+            Some(Token::String(_)) | Some(Token::Number(_)) | Some(Token::Bool(_)) => {
+                self.parse_skalar()
+            }
+            Some(Token::ArrayBegin) | Some(Token::ArrayOpBegin) => self.parse_array(),
+            Some(Token::HashtableBegin) => self.parse_hashtable(),
+            _ => Err(Error::Parser),
+        }
+    }
+
+    // hashtable : HASHTABLE_BEGIN HASHTABLE_END
+    //           | HASHTABLE_BEGIN entry (SEPARATOR entry)* HASHTABLE_END
+    // entry : SKALAR ASSIGN argument
+    // Any number of SEPARATOR is allowed around entries.
+    fn parse_hashtable(&mut self) -> Result<CliArgument> {
         let backtrack = self.input;
-        self.parse_skalar().or_else(|_| {
-            self.parse_array().map_err(|_| {
-                self.input = backtrack;
-                Error::Parser
-            })
-        })
+        if self.parse_newtype_token(Token::HashtableBegin).is_err() {
+            return Err(Error::Parser);
+        }
+        self.enter()?;
+        let hashtable = self.parse_hashtable_entries();
+        self.depth -= 1;
+        if let Some(hashtable) = optional(hashtable)? {
+            return Ok(CliArgument::Hashtable(hashtable));
+        }
+        self.input = backtrack;
+        Err(Error::Parser)
     }
 
-    fn parse_skalar(&mut self) -> Result<CliArgument> {
+    fn parse_hashtable_entries(&mut self) -> Result<IndexMap<String, CliArgument>> {
+        let mut hashtable = IndexMap::new();
+        loop {
+            self.skip_separators();
+            if self.parse_newtype_token(Token::HashtableEnd).is_ok() {
+                return Ok(hashtable);
+            }
+            let key = match self.input.first() {
+                Some(Token::String(key)) | Some(Token::Number(key)) => key.clone(),
+                _ => return Err(Error::Parser),
+            };
+            self.input = &self.input[1..];
+            self.parse_newtype_token(Token::Assign)?;
+            let value = self.parse_argument()?;
+            hashtable.insert(key, value);
+            if !matches!(
+                self.input.first(),
+                Some(Token::Separator) | Some(Token::HashtableEnd)
+            ) {
+                return Err(Error::Parser);
+            }
+        }
+    }
+
+    // cast : CAST element
+    fn parse_cast(&mut self) -> Result<CliArgument> {
         let backtrack = self.input;
-        if !self.input.is_empty() {
-            if let Ok(skalar) = match self.input[0] {
-                Token::String(ref string_token) => Ok(CliArgument::String(string_token.clone())),
-                Token::Number(ref number_token) => {
-                    Ok(CliArgument::Number(Number::parse(number_token).unwrap()))
-                }
-                Token::Bool(bool_token) => Ok(CliArgument::Bool(bool_token)),
-                _ => Err(Error::Parser),
-            } {
+        let cast_type = match self.input.first() {
+            Some(Token::Cast(type_name)) => CastType::parse(type_name).ok_or(Error::Parser)?,
+            _ => return Err(Error::Parser),
+        };
+        self.input = &self.input[1..];
+        // keep the literal text, so [string]007 doesn't lose its leading zeros
+        let casted = match (&cast_type, self.input.first()) {
+            (CastType::String, Some(Token::Number(number_token))) => {
                 self.input = &self.input[1..];
-                return Ok(skalar);
+                Ok(CliArgument::String(number_token.clone()))
+            }
+            _ => {
+                self.enter()?;
+                let element = self.parse_element();
+                self.depth -= 1;
+                element.and_then(|element| cast_type.apply(element).map_err(|_| Error::Parser))
             }
+        };
+        if let Some(casted) = optional(casted)? {
+            return Ok(casted);
         }
         self.input = backtrack;
         Err(Error::Parser)
     }
 
+    fn parse_skalar(&mut self) -> Result<CliArgument> {
+        let skalar = match self.input.first() {
+            Some(Token::String(string_token)) => CliArgument::String(string_token.clone()),
+            Some(Token::Number(number_token)) if self.options.numbers_as_strings => {
+                CliArgument::String(number_token.clone())
+            }
+            Some(Token::Number(number_token)) => CliArgument::Number(
+                Some(number_token)
+                    .filter(|_| self.options.exact_numbers)
+                    .and_then(|number_token| Number::exact(number_token))
+                    .or_else(|| Number::parse(number_token))
+                    .ok_or(Error::Parser)?,
+            ),
+            Some(Token::Bool(bool_token)) => CliArgument::Bool(*bool_token),
+            _ => return Err(Error::Parser),
+        };
+        self.input = &self.input[1..];
+        Ok(skalar)
+    }
+
+    fn skip_separators(&mut self) {
+        while self.parse_newtype_token(Token::Separator).is_ok() {}
+    }
+
     fn parse_newtype_token(&mut self, token: Token) -> Result<Token> {
-        let backtrack = self.input;
-        if !self.input.is_empty() && self.input[0] == token {
+        if self.input.first() == Some(&token) {
             self.input = &self.input[1..];
             return Ok(token);
         }
-        self.input = backtrack;
         Err(Error::Parser)
     }
+
+    // Called before descending into a nested array, hashtable or cast, the caller decrements
+    // the depth when it returns.
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.options.limits.max_depth {
+            return Err(Error::Limit(format!(
+                "nesting is deeper than {} levels",
+                self.options.limits.max_depth
+            )));
+        }
+        self.depth += 1;
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
 pub struct ParameterBinderError {
+    // -Name of the parameter whose value failed, or the value without parameter
     pub failed_arg: Option<String>,
     pub reason: Error,
 }
 
 impl std::fmt::Display for ParameterBinderError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if let Some(failed_arg) = &self.failed_arg {
+        if let (Some(failed_arg), Error::NoPositionalParameter) = (&self.failed_arg, &self.reason) {
+            write!(f, "no positional parameter for value '{}'", failed_arg)
+        } else if let Some(failed_arg) = &self.failed_arg {
             write!(
                 f,
                 "invalid value for argument '{}' ({})",
@@ -411,11 +1094,7 @@ where
 {
     fn is_parameter_name(&self) -> bool {
         let self_as_ref = self.as_ref();
-        self_as_ref.starts_with('-')
-            && self_as_ref
-                .chars()
-                .nth(1)
-                .map_or(false, char::is_alphabetic)
+        self_as_ref.starts_with('-') && self_as_ref.chars().nth(1).is_some_and(char::is_alphabetic)
     }
 
     fn as_parameter_name(&self) -> String {
@@ -429,6 +1108,10 @@ where
 {
     input_args: &'a [T],
     position: usize,
+    switch_parameters: Vec<String>,
+    string_parameters: Vec<String>,
+    positional_parameters: VecDeque<String>,
+    options: ParseOptions,
 }
 
 impl<'a, T> ParameterBinder<'a, T>
@@ -439,6 +1122,62 @@ where
         ParameterBinder {
             input_args,
             position: 0,
+            switch_parameters: Vec::new(),
+            string_parameters: Vec::new(),
+            positional_parameters: VecDeque::new(),
+            options: ParseOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // Switch parameters never take the following argument as value.
+    pub fn with_switch_parameters(mut self, switch_parameters: Vec<String>) -> Self {
+        self.switch_parameters = switch_parameters;
+        self
+    }
+
+    // Numbers passed to string parameters are strings of their literal text, so 007 stays 007.
+    pub fn with_string_parameters(mut self, string_parameters: Vec<String>) -> Self {
+        self.string_parameters = string_parameters;
+        self
+    }
+
+    // Bare values are bound to the given parameters in order. Like in PowerShell, parameters
+    // that are passed by name anywhere in the input don't take positional values.
+    pub fn with_positional_parameters(mut self, positional_parameters: Vec<String>) -> Self {
+        let input_args = self.input_args;
+        self.positional_parameters = positional_parameters
+            .into_iter()
+            .filter(|positional_parameter| {
+                !input_args.iter().any(|arg| {
+                    arg.is_parameter_name()
+                        && arg
+                            .as_parameter_name()
+                            .eq_ignore_ascii_case(positional_parameter)
+                })
+            })
+            .collect();
+        self
+    }
+
+    fn is_switch_parameter(&self, parameter_name: &str) -> bool {
+        self.switch_parameters
+            .iter()
+            .any(|switch_parameter| switch_parameter.eq_ignore_ascii_case(parameter_name))
+    }
+
+    fn options_for(&self, parameter_name: &str) -> ParseOptions {
+        let is_string_parameter = self
+            .string_parameters
+            .iter()
+            .any(|string_parameter| string_parameter.eq_ignore_ascii_case(parameter_name));
+        ParseOptions {
+            numbers_as_strings: self.options.numbers_as_strings || is_string_parameter,
+            ..self.options
         }
     }
 
@@ -454,24 +1193,36 @@ where
             let mut shift_position = 1;
             let parameter_name = current_arg.as_parameter_name();
             let parameter_value = if let Some(next_arg) = self.peek(1) {
-                if next_arg.is_parameter_name() {
+                if next_arg.is_parameter_name() || self.is_switch_parameter(&parameter_name) {
                     CliArgument::Bool(true)
                 } else {
                     shift_position += 1;
-                    from_str(next_arg).map_err(|e| ParameterBinderError {
-                        failed_arg: Some(current_arg.to_owned()),
-                        reason: e,
-                    })?
+                    from_str_with_options(next_arg, &self.options_for(&parameter_name)).map_err(
+                        |e| ParameterBinderError {
+                            failed_arg: Some(current_arg.to_owned()),
+                            reason: e,
+                        },
+                    )?
                 }
             } else {
                 CliArgument::Bool(true)
             };
             self.position += shift_position;
             Ok((parameter_name, parameter_value))
+        } else if let Some(parameter_name) = self.positional_parameters.pop_front() {
+            let parameter_value =
+                from_str_with_options(current_arg, &self.options_for(&parameter_name)).map_err(
+                    |e| ParameterBinderError {
+                        failed_arg: Some(format!("-{}", parameter_name)),
+                        reason: e,
+                    },
+                )?;
+            self.position += 1;
+            Ok((parameter_name, parameter_value))
         } else {
             Err(ParameterBinderError {
                 failed_arg: Some(current_arg.to_owned()),
-                reason: Error::ParameterBinder,
+                reason: Error::NoPositionalParameter,
             })
         }
     }
@@ -480,9 +1231,10 @@ where
         self.position < self.input_args.len()
     }
 
-    fn peek(&self, offset: usize) -> Option<&str> {
-        if self.position + offset < self.input_args.len() {
-            Some(self.input_args[self.position + offset].as_ref())
+    fn peek(&self, offset: usize) -> Option<&'a str> {
+        let input_args = self.input_args;
+        if self.position + offset < input_args.len() {
+            Some(input_args[self.position + offset].as_ref())
         } else {
             None
         }
@@ -504,6 +1256,187 @@ where
     }
 }
 
+// Units Icinga for Windows accepts on threshold numbers, compared case insensitive.
+const THRESHOLD_UNITS: [&str; 21] = [
+    "%", "B", "KB", "MB", "GB", "TB", "PB", "KiB", "MiB", "GiB", "TiB", "PiB", "us", "ms", "s",
+    "m", "h", "d", "w", "M", "y",
+];
+
+#[derive(Debug, PartialEq)]
+pub struct ThresholdError {
+    pub parameter: String,
+    pub threshold: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for ThresholdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid threshold '{}' for parameter '-{}' ({})",
+            self.threshold, self.parameter, self.reason
+        )
+    }
+}
+
+impl std::error::Error for ThresholdError {}
+
+// Parameters like -Warning, -CriticalFree or -WarningEnd take thresholds.
+pub fn is_threshold_parameter(param_name: &str) -> bool {
+    let param_name = param_name.to_lowercase();
+    param_name.starts_with("warning") || param_name.starts_with("critical")
+}
+
+// Checks the Icinga for Windows threshold grammar
+//   threshold : '@'? range
+//   range     : bound | bound ':' | '~' ':' bound | bound ':' bound
+//   bound     : number unit?
+// with units like 20%, 10GB or 10m. Only thresholds made of bounds, '~' and ':' are checked,
+// anything else like regular expressions or dates is left to the plugin. The error names the
+// bad token.
+pub fn check_threshold(threshold: &str) -> std::result::Result<(), String> {
+    let threshold = threshold.trim();
+    let range = threshold.strip_prefix('@').unwrap_or(threshold);
+    let is_range = if range.contains(':') {
+        range
+            .split(':')
+            .all(|part| matches!(part, "" | "~") || is_bound_like(part))
+    } else {
+        is_bound_like(range)
+    };
+    if !is_range {
+        return Ok(());
+    }
+    let (start, end) = match range.split_once(':') {
+        None => return check_threshold_bound(range),
+        Some((start, end)) => (start, end),
+    };
+    if let Some(position) = end.find(':') {
+        return Err(format!("unexpected ':{}'", &end[position + 1..]));
+    }
+    match start {
+        "" => return Err(format!("missing start before ':{}'", end)),
+        "~" if end.is_empty() => return Err(String::from("missing end after '~:'")),
+        "~" => (),
+        start => check_threshold_bound(start)?,
+    }
+    if end.is_empty() {
+        Ok(())
+    } else {
+        check_threshold_bound(end)
+    }
+}
+
+// A number followed by letters or %, whether the number and unit are valid or not.
+fn is_bound_like(bound: &str) -> bool {
+    let digits = bound.strip_prefix(['-', '+']).unwrap_or(bound);
+    let unit_position = digits
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(digits.len());
+    let (number, unit) = digits.split_at(unit_position);
+    number.contains(|c: char| c.is_ascii_digit())
+        && unit.chars().all(|c| c.is_alphabetic() || c == '%')
+}
+
+fn check_threshold_bound(bound: &str) -> std::result::Result<(), String> {
+    let digits = bound.strip_prefix(['-', '+']).unwrap_or(bound);
+    let unit_position = digits
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(digits.len());
+    let (number, unit) = digits.split_at(unit_position);
+    if number.is_empty() || number.parse::<f64>().is_err() {
+        return Err(format!("invalid number '{}'", bound));
+    }
+    if !unit.is_empty()
+        && !THRESHOLD_UNITS
+            .iter()
+            .any(|known_unit| known_unit.eq_ignore_ascii_case(unit))
+    {
+        return Err(format!("unknown unit '{}' in '{}'", unit, bound));
+    }
+    Ok(())
+}
+
+// Checks string thresholds of a threshold parameter, also as array elements.
+pub fn check_threshold_argument(
+    param_name: &str,
+    argument: &CliArgument,
+) -> std::result::Result<(), ThresholdError> {
+    match argument {
+        CliArgument::String(threshold) => {
+            check_threshold(threshold).map_err(|reason| ThresholdError {
+                parameter: param_name.to_owned(),
+                threshold: threshold.clone(),
+                reason,
+            })
+        }
+        CliArgument::Array(array) => array
+            .iter()
+            .try_for_each(|element| check_threshold_argument(param_name, element)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test_threshold {
+    use super::{check_threshold, check_threshold_argument, from_str, is_threshold_parameter};
+
+    #[test]
+    fn test_check_threshold() {
+        for threshold in [
+            "10",
+            "10:",
+            "~:20",
+            "10:20",
+            "@10:20",
+            "-5:-1",
+            "20%",
+            "~:20%",
+            "10GB",
+            "1.5KiB",
+            "10m",
+            "5d:10d",
+            "2026-10-01",
+            "2026-10-01:2026-11-01",
+            "^Run.*$",
+            ".*error",
+            "5\\d\\d",
+            "1\\d+",
+            "~",
+            ".",
+            "Stopped",
+            "",
+        ] {
+            assert_eq!(check_threshold(threshold), Ok(()), "{}", threshold);
+        }
+        let reason = |threshold| check_threshold(threshold).unwrap_err();
+        assert_eq!(reason("10:20:30"), "unexpected ':30'");
+        assert_eq!(reason(":10"), "missing start before ':10'");
+        assert_eq!(reason("~:"), "missing end after '~:'");
+        assert_eq!(reason("1O"), "unknown unit 'O' in '1O'");
+        assert_eq!(reason("10:2O%"), "unknown unit 'O%' in '2O%'");
+        assert_eq!(reason("10%%"), "unknown unit '%%' in '10%%'");
+        assert_eq!(reason("1.2.3"), "invalid number '1.2.3'");
+    }
+
+    #[test]
+    fn test_check_threshold_argument() {
+        assert!(is_threshold_parameter("Warning"));
+        assert!(is_threshold_parameter("criticalFree"));
+        assert!(!is_threshold_parameter("NoWarning"));
+        assert_eq!(
+            check_threshold_argument("Warning", &from_str("'80%',90").unwrap()),
+            Ok(())
+        );
+        assert_eq!(
+            check_threshold_argument("Warning", &from_str("'80%','9O%'").unwrap())
+                .unwrap_err()
+                .to_string(),
+            "invalid threshold '9O%' for parameter '-Warning' (unknown unit 'O%' in '9O%')"
+        );
+    }
+}
+
 #[cfg(test)]
 mod test_number {
     use super::Number;
@@ -514,7 +1447,7 @@ mod test_number {
         assert!(Number::parse("-123") == Some(Number::NegInt(-123)));
         assert!(Number::parse("123.456") == Some(Number::Float(123.456)));
         assert!(Number::parse("-123.456") == Some(Number::Float(-123.456)));
-        assert!(Number::parse("-+1") == None);
+        assert!(Number::parse("-+1").is_none());
     }
 }
 
@@ -584,6 +1517,61 @@ mod test_lexer {
         let lexer = Lexer::from_str(input);
         assert!(lexer.lex().unwrap() == vec![Token::String(r#""'`[]"#.to_owned())]);
     }
+
+    #[test]
+    fn test_lexer_cast() {
+        let input = "[string]123";
+        let lexer = Lexer::from_str(input);
+        assert!(
+            lexer.lex().unwrap()
+                == vec![
+                    Token::Cast("string".to_owned()),
+                    Token::Number("123".to_owned())
+                ]
+        );
+        let input = "[int] '5'";
+        let lexer = Lexer::from_str(input);
+        assert!(
+            lexer.lex().unwrap()
+                == vec![Token::Cast("int".to_owned()), Token::Number("5".to_owned())]
+        );
+        let input = "[string],[int]";
+        let lexer = Lexer::from_str(input);
+        assert!(
+            lexer.lex().unwrap()
+                == vec![
+                    Token::ArrayBegin,
+                    Token::String("string".to_owned()),
+                    Token::ArrayEnd,
+                    Token::Comma,
+                    Token::ArrayBegin,
+                    Token::String("int".to_owned()),
+                    Token::ArrayEnd
+                ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_parantheses() {
+        let input = r#"(ConvertTo-IcingaSecureString (Get-Content 'C:\pw.txt'))"#;
+        let lexer = Lexer::from_str(input);
+        assert!(lexer.lex().unwrap() == vec![Token::String(input.to_owned())]);
+        let input = r#"$(Get-Date),(Get-Item "a)b"),(Write-Output ')(')"#;
+        let lexer = Lexer::from_str(input);
+        assert!(
+            lexer.lex().unwrap()
+                == vec![
+                    Token::String("$(Get-Date)".to_owned()),
+                    Token::Comma,
+                    Token::String(r#"(Get-Item "a)b")"#.to_owned()),
+                    Token::Comma,
+                    Token::String("(Write-Output ')(')".to_owned())
+                ]
+        );
+        let input = r#"(Write-Output "`")" 'it''s)')"#;
+        let lexer = Lexer::from_str(input);
+        assert!(lexer.lex().unwrap() == vec![Token::String(input.to_owned())]);
+    }
 }
 
 #[cfg(test)]
@@ -593,19 +1581,19 @@ mod test_parser {
     #[test]
     fn test_skalars() {
         let tokens = vec![Token::Bool(true)];
-        let mut parser = Parser { input: &tokens };
+        let mut parser = Parser::new(&tokens);
         let result = parser.parse_argument().unwrap();
         let expected = CliArgument::Bool(true);
         assert!(result == expected);
 
         let tokens = vec![Token::Number("123".to_owned())];
-        let mut parser = Parser { input: &tokens };
+        let mut parser = Parser::new(&tokens);
         let result = parser.parse_argument().unwrap();
         let expected = CliArgument::Number(Number::PosInt(123));
         assert!(result == expected);
 
         let tokens = vec![Token::String("Hello World".to_owned())];
-        let mut parser = Parser { input: &tokens };
+        let mut parser = Parser::new(&tokens);
         let result = parser.parse_argument().unwrap();
         let expected = CliArgument::String("Hello World".to_owned());
         assert!(result == expected);
@@ -620,7 +1608,7 @@ mod test_parser {
             Token::Comma,
             Token::Number("123".to_owned()),
         ];
-        let mut parser = Parser { input: &tokens };
+        let mut parser = Parser::new(&tokens);
         let result = parser.parse_argument().unwrap();
         let expected = CliArgument::Array(vec![
             CliArgument::Bool(true),
@@ -633,7 +1621,7 @@ mod test_parser {
     #[test]
     fn test_array_1bool() {
         let tokens = vec![Token::ArrayBegin, Token::Bool(true), Token::ArrayEnd];
-        let mut parser = Parser { input: &tokens };
+        let mut parser = Parser::new(&tokens);
         let result = parser.parse_array().unwrap();
         let expected = CliArgument::Array(vec![CliArgument::Bool(true)]);
         assert!(result == expected);
@@ -648,7 +1636,7 @@ mod test_parser {
             Token::ArrayEnd,
             Token::ArrayEnd,
         ];
-        let mut parser = Parser { input: &tokens };
+        let mut parser = Parser::new(&tokens);
         let result = parser.parse_array().unwrap();
         let expected = CliArgument::Array(vec![CliArgument::Array(vec![CliArgument::Bool(true)])]);
         assert!(result == expected);
@@ -657,8 +1645,11 @@ mod test_parser {
 
 #[cfg(test)]
 mod test_parser_and_lexer {
-    use super::{from_str, CliArgument, Number};
-    use serde_json;
+    use super::{
+        from_str, from_str_with_options, split_script, CliArgument, Error, Limits, Number,
+        ParseOptions,
+    };
+    use proptest::prelude::*;
 
     #[test]
     fn test_example1() {
@@ -785,11 +1776,444 @@ mod test_parser_and_lexer {
                 == r#""(ConvertTo-IcingaSecureString 'my string')""#
         );
     }
+
+    #[test]
+    fn test_example13() {
+        let input = r#"(ConvertTo-IcingaSecureString (Get-Content 'C:\pw (old).txt'))"#;
+        let result = from_str(input).unwrap();
+        let expected = CliArgument::String(input.to_owned());
+        assert!(result == expected);
+    }
+
+    #[test]
+    fn test_example14() {
+        let input = r#"@($(Get-Date -Format "(yyyy)"), "b")"#;
+        let result = from_str(input).unwrap();
+        let expected = CliArgument::Array(vec![
+            CliArgument::String(r#"$(Get-Date -Format "(yyyy)")"#.to_owned()),
+            CliArgument::String("b".to_owned()),
+        ]);
+        assert!(result == expected);
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        assert!(from_str("Grüße").unwrap() == CliArgument::String("Grüße".to_owned()));
+        assert!(from_str("'Größe €'").unwrap() == CliArgument::String("Größe €".to_owned()));
+        assert!(
+            from_str(r#""`ä",(Get-Item 'ö')"#).unwrap()
+                == CliArgument::Array(vec![
+                    CliArgument::String("ä".to_owned()),
+                    CliArgument::String("(Get-Item 'ö')".to_owned()),
+                ])
+        );
+    }
+
+    #[test]
+    fn test_empty_string() {
+        assert!(from_str("''").unwrap() == CliArgument::String(String::new()));
+        assert!(from_str(r#""""#).unwrap() == CliArgument::String(String::new()));
+        assert!(
+            from_str("'',a").unwrap()
+                == CliArgument::Array(vec![
+                    CliArgument::String(String::new()),
+                    CliArgument::String("a".to_owned()),
+                ])
+        );
+    }
+
+    #[test]
+    fn test_verbatim_bool() {
+        assert!(from_str("$True").unwrap() == CliArgument::Bool(true));
+        assert!(from_str("'$True'").unwrap() == CliArgument::String("$True".to_owned()));
+        assert!(from_str("'$False'").unwrap() == CliArgument::String("$False".to_owned()));
+    }
+
+    #[test]
+    fn test_bool_case() {
+        assert!(from_str("$true").unwrap() == CliArgument::Bool(true));
+        assert!(from_str("$FALSE").unwrap() == CliArgument::Bool(false));
+        assert!(from_str("'$true'").unwrap() == CliArgument::String("$true".to_owned()));
+    }
+
+    #[test]
+    fn test_cast() {
+        assert!(from_str("[string]007").unwrap() == CliArgument::String("007".to_owned()));
+        assert!(from_str("[String] 1.10").unwrap() == CliArgument::String("1.10".to_owned()));
+        assert!(from_str("[string]$True").unwrap() == CliArgument::String("True".to_owned()));
+        assert!(from_str("[int]'5'").unwrap() == CliArgument::Number(Number::PosInt(5)));
+        assert!(from_str("[int]2.5").unwrap() == CliArgument::Number(Number::PosInt(2)));
+        assert!(from_str("[int]'-3.5'").unwrap() == CliArgument::Number(Number::NegInt(-4)));
+        assert!(
+            from_str("[long]4294967296").unwrap()
+                == CliArgument::Number(Number::PosInt(4294967296))
+        );
+        assert!(from_str("[double]1").unwrap() == CliArgument::Number(Number::Float(1.0)));
+        assert!(from_str("[bool]0").unwrap() == CliArgument::Bool(false));
+        assert!(from_str("[bool]'False'").unwrap() == CliArgument::Bool(true));
+        assert!(
+            from_str("[array]foo").unwrap()
+                == CliArgument::Array(vec![CliArgument::String("foo".to_owned())])
+        );
+        assert!(
+            from_str("[string]1,[int]'2'").unwrap()
+                == CliArgument::Array(vec![
+                    CliArgument::String("1".to_owned()),
+                    CliArgument::Number(Number::PosInt(2)),
+                ])
+        );
+        assert!(
+            from_str("[[string]1, 2]").unwrap()
+                == CliArgument::Array(vec![
+                    CliArgument::String("1".to_owned()),
+                    CliArgument::Number(Number::PosInt(2)),
+                ])
+        );
+        assert!(
+            from_str("[string]").unwrap()
+                == CliArgument::Array(vec![CliArgument::String("string".to_owned())])
+        );
+    }
+
+    #[test]
+    fn test_hashtable() {
+        let input = "@{ Name = 'foo'; Ids = 1,2\n Nested = @{} }";
+        let result = from_str(input).unwrap();
+        assert!(
+            serde_json::to_string(&result).unwrap() == r#"{"Name":"foo","Ids":[1,2],"Nested":{}}"#
+        );
+        assert!(from_str("@{}").unwrap() == CliArgument::Hashtable(Default::default()));
+        assert_eq!(from_str("@{ Name }"), Err(Error::Parser));
+        assert_eq!(from_str("@{ Name = 'foo' Other = 1 }"), Err(Error::Parser));
+    }
+
+    #[test]
+    fn test_range() {
+        let numbers = |numbers: &[i64]| {
+            numbers
+                .iter()
+                .map(|number| CliArgument::Number(Number::from_i64(*number)))
+                .collect::<Vec<CliArgument>>()
+        };
+        assert!(from_str("0..3").unwrap() == CliArgument::Array(numbers(&[0, 1, 2, 3])));
+        assert!(
+            from_str("8085..8083").unwrap() == CliArgument::Array(numbers(&[8085, 8084, 8083]))
+        );
+        assert!(from_str("-1..1").unwrap() == CliArgument::Array(numbers(&[-1, 0, 1])));
+        assert!(from_str("5..5").unwrap() == CliArgument::Array(numbers(&[5])));
+        assert!(from_str("0..2,7").unwrap() == CliArgument::Array(numbers(&[0, 1, 2, 7])));
+        assert!(from_str("@(7, 0..1)").unwrap() == CliArgument::Array(numbers(&[7, 0, 1])));
+        assert!(
+            from_str("[0..1,[2..3]]").unwrap()
+                == CliArgument::Array(vec![
+                    CliArgument::Number(Number::PosInt(0)),
+                    CliArgument::Number(Number::PosInt(1)),
+                    CliArgument::Array(numbers(&[2, 3])),
+                ])
+        );
+        // quoted or not looking like an expression remains a string
+        assert!(from_str("'0..7'").unwrap() == CliArgument::String("0..7".to_owned()));
+        assert!(from_str(r"..\foo").unwrap() == CliArgument::String(r"..\foo".to_owned()));
+        assert!(from_str("a..b").unwrap() == CliArgument::String("a..b".to_owned()));
+    }
+
+    #[test]
+    fn test_range_errors() {
+        assert_eq!(
+            from_str("1..$max"),
+            Err(Error::Expression(
+                "unsupported range '1..$max', only integer constants are allowed".to_owned()
+            ))
+        );
+        assert!(matches!(from_str("0.5..2"), Err(Error::Expression(_))));
+        assert!(matches!(from_str("1..x"), Err(Error::Expression(_))));
+        assert_eq!(
+            from_str("0..10000"),
+            Err(Error::Expression(
+                "range '0..10000' has more than 10000 elements".to_owned()
+            ))
+        );
+        assert!(from_str("1..10000").is_ok());
+    }
+
+    #[test]
+    fn test_temporal_cast() {
+        let string = |string: &str| Ok(CliArgument::String(string.to_owned()));
+        assert_eq!(
+            from_str("[datetime]'2026-10-01 00:00'"),
+            string("2026-10-01T00:00:00")
+        );
+        assert_eq!(
+            from_str("[DateTime]2026-10-01"),
+            string("2026-10-01T00:00:00")
+        );
+        assert_eq!(from_str("[timespan]'01:00:00'"), string("01:00:00"));
+        assert_eq!(from_str("[TimeSpan]1.5h"), string("01:30:00"));
+        assert_eq!(from_str("[timespan]3"), string("3.00:00:00"));
+        assert_eq!(from_str("[datetime]'01.10.2026'"), Err(Error::Parser));
+        assert_eq!(from_str("[timespan]'1 day'"), Err(Error::Parser));
+        assert_eq!(from_str("[timespan]@(1,2)"), Err(Error::Parser));
+        // without a cast they stay as they are
+        assert_eq!(from_str("1.5h"), string("1.5h"));
+    }
+
+    #[test]
+    fn test_cast_errors() {
+        assert_eq!(from_str("[foo]123"), Err(Error::Parser));
+        assert_eq!(from_str("[int]'abc'"), Err(Error::Parser));
+        assert_eq!(from_str("[int]4294967296"), Err(Error::Parser));
+        assert_eq!(from_str("[int]@(1,2)"), Err(Error::Parser));
+    }
+
+    #[test]
+    fn test_limits() {
+        let nested = |open: &str, close: &str, depth: usize| {
+            format!("{}1{}", open.repeat(depth), close.repeat(depth))
+        };
+        let too_deep = Err(Error::Limit("nesting is deeper than 32 levels".to_owned()));
+        assert!(from_str(&nested("[", "]", 32)).is_ok());
+        assert_eq!(from_str(&nested("[", "]", 33)), too_deep);
+        assert_eq!(from_str(&nested("[", "]", 10000)), too_deep);
+        assert_eq!(from_str(&nested("@(", ")", 10000)), too_deep);
+        assert_eq!(from_str(&nested("@{a=", "}", 10000)), too_deep);
+        assert_eq!(from_str(&nested("[int]", "", 10000)), too_deep);
+        assert_eq!(from_str(&nested("[", "", 10000)), too_deep);
+        assert_eq!(from_str(&nested("[", "", 3)), Err(Error::Parser));
+
+        let options = ParseOptions {
+            limits: Limits {
+                max_depth: 2,
+                max_tokens: 7,
+                max_length: 10,
+            },
+            ..Default::default()
+        };
+        assert!(from_str_with_options("@(@(1))", &options).is_ok());
+        assert_eq!(
+            from_str_with_options("@(@(@(1)))", &options),
+            Err(Error::Limit("nesting is deeper than 2 levels".to_owned()))
+        );
+        assert_eq!(
+            from_str_with_options("1,2,3", &options).unwrap(),
+            from_str("1,2,3").unwrap()
+        );
+        assert_eq!(
+            from_str_with_options("1,2,3,4,5", &options),
+            Err(Error::Limit("argument has more than 7 tokens".to_owned()))
+        );
+        assert_eq!(
+            from_str_with_options("12345678901", &options)
+                .unwrap_err()
+                .to_string(),
+            "parser limit exceeded, argument is longer than 10 bytes"
+        );
+    }
+
+    #[test]
+    fn test_comments_and_separators() {
+        let strings = |strings: &[&str]| {
+            CliArgument::Array(
+                strings
+                    .iter()
+                    .map(|string| CliArgument::String(string.to_string()))
+                    .collect(),
+            )
+        };
+        assert_eq!(from_str(r#"@("a"; "b")"#), Ok(strings(&["a", "b"])));
+        assert_eq!(
+            from_str("@(\r\n  'a'\r\n  'b'\r\n)"),
+            Ok(strings(&["a", "b"]))
+        );
+        assert_eq!(from_str("@(a,b;;c\n)"), Ok(strings(&["a", "b", "c"])));
+        assert_eq!(from_str("@(a,\n  b)"), Ok(strings(&["a", "b"])));
+        assert_eq!(from_str("@(;\n)"), Ok(strings(&[])));
+        assert_eq!(
+            from_str("@(\n  # services\n  'a' # first\n  <# second #> 'b'\n)"),
+            Ok(strings(&["a", "b"]))
+        );
+        assert_eq!(from_str("a#b"), Ok(CliArgument::String("a#b".to_owned())));
+        assert_eq!(from_str("a #b"), Ok(CliArgument::String("a".to_owned())));
+        assert_eq!(from_str("'#a'"), Ok(CliArgument::String("#a".to_owned())));
+        assert_eq!(from_str("a,\nb"), Ok(strings(&["a", "b"])));
+        assert_eq!(from_str("a;b"), Ok(CliArgument::String("a;b".to_owned())));
+        assert_eq!(from_str("<# open"), Err(Error::Lexer));
+
+        let mut hashtable = indexmap::IndexMap::new();
+        hashtable.insert("a".to_owned(), strings(&["x", "y"]));
+        hashtable.insert("b".to_owned(), CliArgument::Number(Number::PosInt(2)));
+        assert_eq!(
+            from_str("@{\n  # comment\n  a = @('x'\n'y')\n  b = 2 # two\n}"),
+            Ok(CliArgument::Hashtable(hashtable))
+        );
+    }
+
+    #[test]
+    fn test_herestring() {
+        assert_eq!(
+            from_str("@'\n'a' \"b\"\r\n$True\n'@"),
+            Ok(CliArgument::String("'a' \"b\"\r\n$True".to_owned()))
+        );
+        assert_eq!(
+            from_str("@(@\"\r\na\r\n\"@, @'\n'@)"),
+            Ok(CliArgument::Array(vec![
+                CliArgument::String("a".to_owned()),
+                CliArgument::String("".to_owned())
+            ]))
+        );
+        assert_eq!(from_str("@'\na"), Err(Error::Lexer));
+    }
+
+    #[test]
+    fn test_split_script() {
+        assert_eq!(
+            split_script("try { Use-Icinga; } catch { exit 3; }; exit Foo -A @{ a = 1; b = 2 }"),
+            Ok(vec![
+                vec!["try", "{ Use-Icinga; }", "catch", "{ exit 3; }"],
+                vec!["exit", "Foo", "-A", "@{ a = 1; b = 2 }"]
+            ])
+        );
+        assert_eq!(
+            split_script("Foo # -A\n<# -B; #> Bar -C 'x;y'`;z\n"),
+            Ok(vec![vec!["Foo"], vec!["Bar", "-C", "'x;y'`;z"]])
+        );
+        assert_eq!(split_script(" ;\n"), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_exact_numbers() {
+        let options = ParseOptions {
+            exact_numbers: true,
+            ..Default::default()
+        };
+        let exact = |literal: &str| CliArgument::Number(Number::Exact(literal.to_owned()));
+        let parsed =
+            from_str_with_options("18446744073709551616,1.10,007,-0.1e3", &options).unwrap();
+        assert_eq!(
+            parsed,
+            CliArgument::Array(vec![
+                exact("18446744073709551616"),
+                exact("1.10"),
+                CliArgument::Number(Number::PosInt(7)),
+                exact("-0.1e3"),
+            ])
+        );
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            "[18446744073709551616,1.10,7,-0.1e3]"
+        );
+        assert_eq!(
+            serde_json::to_string(&from_str("18446744073709551616,1.10").unwrap()).unwrap(),
+            "[1.8446744073709552e19,1.1]"
+        );
+        assert_eq!(
+            from_str_with_options("[int]2.5", &options),
+            Ok(CliArgument::Number(Number::PosInt(2)))
+        );
+        assert_eq!(
+            from_str_with_options("[string]1.10", &options),
+            Ok(CliArgument::String("1.10".to_owned()))
+        );
+        assert_eq!(
+            super::CastType::String.apply(exact("1.10")),
+            Ok(CliArgument::String("1.10".to_owned()))
+        );
+        assert_eq!(super::to_string(&exact("1.10")), "1.10");
+    }
+
+    proptest! {
+        #[test]
+        fn test_no_panic(input in "[\\[\\](){}@,;=$'\"`a1 .]{0,64}") {
+            let _ = from_str(&input);
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            from_str(r#"json:'{"a":[1,null],"b":"it''s"}'"#),
+            Ok(CliArgument::Json(
+                serde_json::json!({"a": [1, null], "b": "it's"})
+            ))
+        );
+        assert_eq!(
+            from_str("json:[1,null]"),
+            Ok(CliArgument::Json(serde_json::json!([1, null])))
+        );
+        assert_eq!(
+            from_str(r#"json:'{"a":[1,nul]}'"#),
+            Err(Error::Expression(
+                "invalid JSON argument (expected ident at line 1 column 12)".to_owned()
+            ))
+        );
+    }
+}
+
+#[cfg(test)]
+pub mod test_printer {
+    use super::{from_str, to_string, CliArgument, Number};
+    use proptest::prelude::*;
+
+    pub fn cli_argument() -> impl Strategy<Value = CliArgument> {
+        let float = prop::num::f64::POSITIVE
+            | prop::num::f64::NEGATIVE
+            | prop::num::f64::NORMAL
+            | prop::num::f64::SUBNORMAL
+            | prop::num::f64::ZERO;
+        let skalar = prop_oneof![
+            any::<bool>().prop_map(CliArgument::Bool),
+            any::<u64>().prop_map(|unsigned| CliArgument::Number(Number::PosInt(unsigned))),
+            (i64::MIN..0).prop_map(|signed| CliArgument::Number(Number::NegInt(signed))),
+            float.prop_map(|float| CliArgument::Number(Number::Float(float))),
+            any::<String>().prop_map(CliArgument::String),
+        ];
+        skalar.prop_recursive(4, 64, 8, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..8).prop_map(CliArgument::Array),
+                prop::collection::vec((any::<String>(), inner), 0..8)
+                    .prop_map(|entries| CliArgument::Hashtable(entries.into_iter().collect())),
+            ]
+        })
+    }
+
+    #[test]
+    fn test_to_string() {
+        let print = |input: &str| to_string(&from_str(input).unwrap());
+        assert_eq!(print("foo"), "foo");
+        assert_eq!(print("'foo bar'"), "'foo bar'");
+        assert_eq!(print(r#""it's""#), r#""it's""#);
+        assert_eq!(print(r#""it's `"quoted`"""#), r#""it's `"quoted`"""#);
+        assert_eq!(print("''"), "''");
+        assert_eq!(print("'-foo'"), "'-foo'");
+        assert_eq!(print("'$True'"), "'$True'");
+        assert_eq!(print("[string]007"), "[string]007");
+        assert_eq!(print("-12"), "-12");
+        assert_eq!(print("[double]1"), "1.0");
+        assert_eq!(print("$False"), "$False");
+        assert_eq!(print("'json:foo'"), "'json:foo'");
+        assert_eq!(
+            print(r#"json:{"b":[1,null],"a":"it's"}"#),
+            r#"json:'{"b":[1,null],"a":"it''s"}'"#
+        );
+        assert_eq!(print("foo,[1,'a b']"), "@(foo,@(1,'a b'))");
+        assert_eq!(print("@()"), "@()");
+        assert_eq!(print("'0..7'"), "'0..7'");
+        assert_eq!(
+            print("@{ Name = 'a;b'; 'x y' = @{} }"),
+            "@{Name='a;b';'x y'=@{}}"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(argument in cli_argument()) {
+            prop_assert_eq!(from_str(&to_string(&argument)).unwrap(), argument);
+        }
+    }
 }
 
 #[cfg(test)]
 mod test_parameter_binder {
-    use crate::ps::{CliArgument, Number, ParameterBinder};
+    use crate::ps::{CliArgument, Error, Number, ParameterBinder};
 
     #[test]
     fn test_parameter_binder() {
@@ -840,4 +2264,68 @@ mod test_parameter_binder {
             )
         );
     }
+
+    #[test]
+    fn test_parameter_binder_switch() {
+        let input_args = vec!["-NoPerfData", "foo", "-Critical", "90"];
+        let mut pb =
+            ParameterBinder::new(&input_args).with_switch_parameters(vec!["noperfdata".to_owned()]);
+        assert_eq!(
+            pb.next_parameter_pair().unwrap(),
+            ("NoPerfData".to_owned(), CliArgument::Bool(true))
+        );
+        let err = pb.next_parameter_pair().unwrap_err();
+        assert_eq!(err.failed_arg, Some("foo".to_owned()));
+        assert_eq!(err.reason, Error::NoPositionalParameter);
+    }
+
+    #[test]
+    fn test_parameter_binder_positional() {
+        let positional_parameters = vec![
+            "Include".to_owned(),
+            "Warning".to_owned(),
+            "Critical".to_owned(),
+        ];
+        let input_args = vec!["foo,bar", "-Warning", "80", "90"];
+        let pb = ParameterBinder::new(&input_args)
+            .with_positional_parameters(positional_parameters.clone());
+        let bound: Vec<(String, CliArgument)> = pb.map(Result::unwrap).collect();
+        assert_eq!(
+            bound,
+            vec![
+                (
+                    "Include".to_owned(),
+                    CliArgument::Array(vec![
+                        CliArgument::String("foo".to_owned()),
+                        CliArgument::String("bar".to_owned())
+                    ])
+                ),
+                (
+                    "Warning".to_owned(),
+                    CliArgument::Number(Number::PosInt(80))
+                ),
+                (
+                    "Critical".to_owned(),
+                    CliArgument::Number(Number::PosInt(90))
+                ),
+            ]
+        );
+
+        // surplus positionals are reported
+        let input_args = vec!["foo", "80", "90", "100"];
+        let mut pb =
+            ParameterBinder::new(&input_args).with_positional_parameters(positional_parameters);
+        assert!(pb.by_ref().take(3).all(|bound| bound.is_ok()));
+        let err = pb.next().unwrap().unwrap_err();
+        assert_eq!(err.failed_arg, Some("100".to_owned()));
+        assert_eq!(err.reason, Error::NoPositionalParameter);
+        assert_eq!(err.to_string(), "no positional parameter for value '100'");
+
+        // errors name positional parameters like named ones
+        let input_args = vec!["@(1"];
+        let mut pb = ParameterBinder::new(&input_args)
+            .with_positional_parameters(vec!["Include".to_owned()]);
+        let err = pb.next().unwrap().unwrap_err();
+        assert_eq!(err.failed_arg, Some("-Include".to_owned()));
+    }
 }
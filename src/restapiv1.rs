@@ -1,8 +1,13 @@
 use crate::icinga::{ExitCode, IcingaTermination};
-use crate::ps;
+use crate::perfdata::{self, PerfdataError, PerfdataPoint, PerfdataRules, ThresholdOverride};
+use crate::ps::{self, ParameterBinderToken};
+use crate::schema::{CommandSchema, SchemaError};
+use crate::secret::{SecretError, SecretResolver};
+use crate::state::StateRules;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::convert::Infallible;
 use std::fmt;
 
 type EmptyObject = HashMap<(), ()>;
@@ -16,14 +21,14 @@ pub enum Argument {
     DummyArgument(bool),
 }
 
-#[derive(PartialEq, Debug, Deserialize)]
+#[derive(PartialEq, Debug, Clone, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum Exitcode {
     Executed(i32),
     NotExecuted(EmptyObject),
 }
 
-#[derive(PartialEq, Debug, Deserialize)]
+#[derive(PartialEq, Debug, Clone, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum Perfdata {
     Single(String),
@@ -31,7 +36,7 @@ pub enum Perfdata {
     None(EmptyObject),
 }
 
-#[derive(PartialEq, Debug, Deserialize)]
+#[derive(PartialEq, Debug, Clone, Deserialize, Serialize)]
 pub struct CheckerResult {
     pub exitcode: Exitcode,
     pub checkresult: String,
@@ -45,17 +50,178 @@ impl std::convert::TryFrom<&[String]> for CommandArguments {
     type Error = ps::ParameterBinderError;
 
     fn try_from(args: &[String]) -> Result<Self, Self::Error> {
+        CommandArguments::from_args(args, &ps::ParseOptions::default())
+    }
+}
+
+impl From<IndexMap<String, ps::CliArgument>> for CommandArguments {
+    fn from(command_map: IndexMap<String, ps::CliArgument>) -> Self {
+        CommandArguments(command_map)
+    }
+}
+
+impl CommandArguments {
+    pub fn from_args(
+        args: &[String],
+        options: &ps::ParseOptions,
+    ) -> Result<Self, ps::ParameterBinderError> {
+        let mut command_map: IndexMap<String, ps::CliArgument> = IndexMap::new();
+        let param_binder = ps::ParameterBinder::new(args).with_options(*options);
+        for params in param_binder {
+            let (param_name, param_value) = params?;
+            command_map.insert(param_name, param_value);
+        }
+        Ok(CommandArguments(command_map))
+    }
+
+    // Like try_from, but parameter names, switches, positions and value types are taken from
+    // the schema of the command.
+    pub fn bind(
+        args: &[String],
+        schema: &CommandSchema,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        CommandArguments::bind_with_options(args, schema, &ps::ParseOptions::default())
+    }
+
+    pub fn bind_with_options(
+        args: &[String],
+        schema: &CommandSchema,
+        options: &ps::ParseOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let args = args
+            .iter()
+            .map(|arg| {
+                if arg.is_parameter_name() {
+                    let (param_name, _) = schema.resolve(&arg.as_parameter_name())?;
+                    Ok(format!("-{}", param_name))
+                } else {
+                    Ok(arg.clone())
+                }
+            })
+            .collect::<Result<Vec<String>, SchemaError>>()?;
         let mut command_map: IndexMap<String, ps::CliArgument> = IndexMap::new();
-        let param_binder = ps::ParameterBinder::new(args);
+        let param_binder = ps::ParameterBinder::new(&args)
+            .with_options(*options)
+            .with_switch_parameters(schema.switch_parameters())
+            .with_string_parameters(schema.string_parameters())
+            .with_positional_parameters(schema.positional_parameters());
         for params in param_binder {
             let (param_name, param_value) = params?;
+            let (_, param_schema) = schema.resolve(&param_name)?;
+            if command_map.contains_key(&param_name) {
+                return Err(SchemaError::DuplicateParameter {
+                    parameter: param_name,
+                }
+                .into());
+            }
+            let param_value = param_schema.coerce(&param_name, param_value)?;
             command_map.insert(param_name, param_value);
         }
         Ok(CommandArguments(command_map))
     }
+
+    // Like bind, for arguments that are structured already and need no binding.
+    pub fn coerce(self, schema: &CommandSchema) -> Result<Self, SchemaError> {
+        let mut command_map: IndexMap<String, ps::CliArgument> = IndexMap::new();
+        for (param_name, param_value) in self.0 {
+            let (param_name, param_schema) = schema.resolve(&param_name)?;
+            if command_map.contains_key(param_name) {
+                return Err(SchemaError::DuplicateParameter {
+                    parameter: param_name.to_owned(),
+                });
+            }
+            let param_value = param_schema.coerce(param_name, param_value)?;
+            command_map.insert(param_name.to_owned(), param_value);
+        }
+        Ok(CommandArguments(command_map))
+    }
+
+    // Inverse of try_from, renders the arguments as command line tokens.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (param_name, param_value) in &self.0 {
+            args.push(format!("-{}", param_name));
+            if *param_value != ps::CliArgument::Bool(true) {
+                args.push(ps::to_string(param_value));
+            }
+        }
+        args
+    }
+
+    // Fails on the first threshold parameter like -Warning with a malformed threshold.
+    pub fn check_thresholds(&self) -> Result<(), ps::ThresholdError> {
+        self.0
+            .iter()
+            .filter(|(param_name, _)| ps::is_threshold_parameter(param_name))
+            .try_for_each(|(param_name, param_value)| {
+                ps::check_threshold_argument(param_name, param_value)
+            })
+    }
+
+    // Only meant to build the request body, the result must not be displayed anywhere.
+    pub fn resolve_secrets(&self, resolver: &SecretResolver) -> Result<Self, SecretError> {
+        let mut command_map: IndexMap<String, ps::CliArgument> = IndexMap::new();
+        for (param_name, param_value) in &self.0 {
+            command_map.insert(param_name.clone(), resolver.resolve_argument(param_value)?);
+        }
+        Ok(CommandArguments(command_map))
+    }
 }
 
 impl Perfdata {
+    // The entries of all perfdata strings in order, empty if there's no perfdata.
+    pub fn points(&self) -> Result<Vec<PerfdataPoint>, PerfdataError> {
+        match self {
+            Perfdata::None(_) => Ok(Vec::new()),
+            Perfdata::Single(single_perfdata) => perfdata::parse(single_perfdata),
+            Perfdata::Multiple(multiple_perfdatas) => {
+                let mut points = Vec::new();
+                for single_perfdata in multiple_perfdatas {
+                    points.extend(perfdata::parse(single_perfdata)?);
+                }
+                Ok(points)
+            }
+        }
+    }
+
+    // Rebuilds the perfdata from the entries map makes of all perfdata strings. Without
+    // perfdata, map isn't called.
+    pub fn map_entries<E>(
+        &self,
+        map: impl FnOnce(&[String]) -> Result<Vec<String>, E>,
+    ) -> Result<Perfdata, E> {
+        let entries = match self {
+            Perfdata::None(_) => return Ok(Perfdata::None(EmptyObject::new())),
+            Perfdata::Single(single_perfdata) => map(std::slice::from_ref(single_perfdata))?,
+            Perfdata::Multiple(multiple_perfdatas) => map(multiple_perfdatas)?,
+        };
+        Ok(Perfdata::Multiple(entries))
+    }
+
+    // See perfdata::normalize, returns the repaired perfdata and the fixes made.
+    pub fn normalize(&self) -> (Perfdata, Vec<String>) {
+        let mut fixes = Vec::new();
+        let normalized = self.map_entries(|perfdatas| {
+            let (entries, normalize_fixes) = perfdata::normalize(perfdatas);
+            fixes = normalize_fixes;
+            Ok::<_, Infallible>(entries)
+        });
+        match normalized {
+            Ok(perfdata) => (perfdata, fixes),
+            Err(never) => match never {},
+        }
+    }
+
+    // Rebuilds the perfdata from the entries remaining after filtering and renaming.
+    pub fn apply_rules(&self, rules: &PerfdataRules) -> Result<Perfdata, PerfdataError> {
+        self.map_entries(|perfdatas| rules.apply(perfdatas))
+    }
+
+    // Rescales all entries to the canonical unit of their family, B, s or %.
+    pub fn normalize_units(&self) -> Result<Perfdata, PerfdataError> {
+        self.map_entries(perfdata::normalize_units)
+    }
+
     fn valid(&self) -> bool {
         match self {
             Perfdata::None(_) => false,
@@ -79,17 +245,80 @@ impl fmt::Display for Perfdata {
     }
 }
 
+impl CheckerResult {
+    // Evaluates the perfdata again with the overridden thresholds. If the state of the plugin
+    // is the worst state of its perfdata, it's replaced by the new worst state. A worse state
+    // from elsewhere is kept, UNKNOWN and not executed results aren't touched.
+    pub fn override_thresholds(
+        self,
+        overrides: &[ThresholdOverride],
+    ) -> Result<CheckerResult, PerfdataError> {
+        let mut states = None;
+        let perfdata = self.perfdata.map_entries(|perfdatas| {
+            let (entries, state_before, state_after) =
+                perfdata::override_thresholds(perfdatas, overrides)?;
+            states = Some((state_before, state_after));
+            Ok(entries)
+        })?;
+        let (state_before, state_after) = match states {
+            Some(states) => states,
+            None => return Ok(self),
+        };
+        let exitcode = match self.exitcode {
+            Exitcode::Executed(code) if code == state_before as i32 => {
+                Exitcode::Executed(state_after as i32)
+            }
+            Exitcode::Executed(code) if code < ExitCode::Unknown as i32 => {
+                Exitcode::Executed(perfdata::worst(ExitCode::from_i32(code), state_after) as i32)
+            }
+            exitcode => exitcode,
+        };
+        Ok(CheckerResult {
+            exitcode,
+            checkresult: self.checkresult,
+            perfdata,
+        })
+    }
+
+    // Adjusts the state by the rules, every change is noted in an additional line of the
+    // check output.
+    pub fn apply_state_rules(self, rules: &StateRules) -> CheckerResult {
+        let state = match self.exitcode {
+            Exitcode::Executed(code) => Some(code),
+            Exitcode::NotExecuted(_) => None,
+        };
+        let (exitcode, notes) = rules.apply(state, &self.checkresult);
+        if notes.is_empty() {
+            return self;
+        }
+        let mut checkresult = self.checkresult;
+        for note in notes {
+            checkresult.push('\n');
+            checkresult.push_str(&note);
+        }
+        CheckerResult {
+            exitcode: Exitcode::Executed(exitcode as i32),
+            checkresult,
+            perfdata: self.perfdata,
+        }
+    }
+}
+
+// Per the plugin API, the first line is the short output and the following lines are the long
+// output. The perfdata goes right after the short output, instead of being glued to the last
+// long output line.
 impl fmt::Display for CheckerResult {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let icinga_cr: String = self.checkresult.replace("\r\n", "\n");
-        if self.perfdata.valid() {
-            write!(
-                f,
-                "{}",
-                [icinga_cr, format!("{}", self.perfdata)].join(" | ")
-            )
-        } else {
-            write!(f, "{}", icinga_cr)
+        let icinga_cr = icinga_cr.trim_end_matches('\n');
+        if !self.perfdata.valid() {
+            return write!(f, "{}", icinga_cr);
+        }
+        match icinga_cr.split_once('\n') {
+            Some((short_output, long_output)) => {
+                write!(f, "{} | {}\n{}", short_output, self.perfdata, long_output)
+            }
+            None => write!(f, "{} | {}", icinga_cr, self.perfdata),
         }
     }
 }
@@ -111,8 +340,13 @@ impl IcingaTermination for CheckerResult {
 #[cfg(test)]
 mod tests {
     use super::{Argument, CheckerResult, CommandArguments, EmptyObject, Exitcode, Perfdata};
+    use crate::perfdata::{PerfdataRules, ThresholdOverride};
+    use crate::ps::test_printer::cli_argument;
     use crate::ps::{CliArgument, Error, Number};
-    use serde_json;
+    use crate::schema::CommandSchema;
+    use crate::secret::SecretResolver;
+    use crate::state::StateRules;
+    use proptest::prelude::*;
     use std::collections::HashMap;
     use std::convert::{TryFrom, TryInto};
 
@@ -141,7 +375,7 @@ mod tests {
             String::from("baz"),
         ];
         let err = CommandArguments::try_from(args.as_slice()).unwrap_err();
-        assert_eq!(err.reason, Error::ParameterBinder);
+        assert_eq!(err.reason, Error::NoPositionalParameter);
 
         // parameters with arguments are inserted as key value pairs
         let args = vec![
@@ -182,6 +416,201 @@ mod tests {
         assert_eq!(cmdargs.0.get("switch").unwrap(), &CliArgument::Bool(true));
     }
 
+    #[test]
+    fn test_serialize_json_argument() {
+        let args = vec![
+            String::from("-Filter"),
+            String::from(r#"json:'{"b":[1,null],"a":{}}'"#),
+        ];
+        let cmdargs = CommandArguments::try_from(args.as_slice()).unwrap();
+        assert_eq!(
+            serde_json::to_string(&cmdargs).unwrap(),
+            r#"{"Filter":{"b":[1,null],"a":{}}}"#
+        );
+    }
+
+    #[test]
+    fn test_bind_commandarguments() {
+        let schema: CommandSchema = serde_json::from_str(
+            r#"{"parameters": {
+                "Include": {"type": "array", "items": "string", "aliases": ["Service"], "position": 0},
+                "Verbosity": {"type": "int", "position": 1},
+                "NoPerfData": {"type": "switch"},
+                "Id": {"type": "string"}
+            }}"#,
+        )
+        .unwrap();
+        let args = vec![
+            String::from("-include"),
+            String::from("1234"),
+            String::from("-Verb"),
+            String::from("'2'"),
+        ];
+        let cmdargs = CommandArguments::bind(&args, &schema).unwrap();
+        assert_eq!(
+            serde_json::to_string(&cmdargs).unwrap(),
+            r#"{"Include":["1234"],"Verbosity":2}"#
+        );
+
+        // positional values go to the parameters not passed by name, in order of position
+        let args = vec![
+            String::from("-NoPerfData"),
+            String::from("2"),
+            String::from("-Service"),
+            String::from("foo"),
+        ];
+        let cmdargs = CommandArguments::bind(&args, &schema).unwrap();
+        assert_eq!(
+            serde_json::to_string(&cmdargs).unwrap(),
+            r#"{"NoPerfData":true,"Verbosity":2,"Include":["foo"]}"#
+        );
+
+        // numbers passed to string parameters keep their literal text
+        let args = vec![
+            String::from("-Include"),
+            String::from("007,1.10"),
+            String::from("-Id"),
+            String::from("0042"),
+            String::from("-Verbosity"),
+            String::from("007"),
+        ];
+        let cmdargs = CommandArguments::bind(&args, &schema).unwrap();
+        assert_eq!(
+            serde_json::to_string(&cmdargs).unwrap(),
+            r#"{"Include":["007","1.10"],"Id":"0042","Verbosity":7}"#
+        );
+
+        let args = vec![String::from("foo"), String::from("2"), String::from("3")];
+        let err = CommandArguments::bind(&args, &schema).unwrap_err();
+        assert_eq!(err.to_string(), "no positional parameter for value '3'");
+
+        let args = vec![String::from("-Exclude"), String::from("foo")];
+        let err = CommandArguments::bind(&args, &schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown parameter '-Exclude', did you mean '-Include'?"
+        );
+
+        // like PowerShell, a parameter can't be bound twice, not even by an alias
+        let args = vec![
+            String::from("-Include"),
+            String::from("x"),
+            String::from("-Service"),
+            String::from("y"),
+        ];
+        let err = CommandArguments::bind(&args, &schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parameter '-Include' is specified more than once"
+        );
+    }
+
+    #[test]
+    fn test_coerce_commandarguments() {
+        let schema: CommandSchema = serde_json::from_str(
+            r#"{"parameters": {"Warning": {"type": "int"}, "Include": {"type": "array"}}}"#,
+        )
+        .unwrap();
+        let parameters =
+            crate::input::parse("warn: '80'\nInclude: a\n", crate::input::InputFormat::Yaml)
+                .unwrap();
+        let cmdargs = CommandArguments::from(parameters).coerce(&schema).unwrap();
+        assert_eq!(
+            serde_json::to_string(&cmdargs).unwrap(),
+            r#"{"Warning":80,"Include":["a"]}"#
+        );
+        let parameters = crate::input::parse(
+            "Include: a
+include: b
+",
+            crate::input::InputFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(
+            CommandArguments::from(parameters)
+                .coerce(&schema)
+                .unwrap_err()
+                .to_string(),
+            "parameter '-Include' is specified more than once"
+        );
+    }
+
+    #[test]
+    fn test_commandarguments_to_args() {
+        let args = vec![
+            String::from("-Include"),
+            String::from("foo,'bar baz'"),
+            String::from("-NoPerfData"),
+            String::from("-Warning"),
+            String::from("'-10:20'"),
+        ];
+        let cmdargs = CommandArguments::try_from(args.as_slice()).unwrap();
+        assert_eq!(
+            cmdargs.to_args(),
+            vec![
+                "-Include",
+                "@(foo,'bar baz')",
+                "-NoPerfData",
+                "-Warning",
+                "'-10:20'"
+            ]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_commandarguments_round_trip(
+            command_map in prop::collection::vec(("[A-Za-z][A-Za-z0-9]{0,10}", cli_argument()), 0..8)
+        ) {
+            let cmdargs = CommandArguments(command_map.into_iter().collect());
+            let round_tripped = CommandArguments::try_from(cmdargs.to_args().as_slice()).unwrap();
+            prop_assert_eq!(
+                serde_json::to_value(&round_tripped).unwrap(),
+                serde_json::to_value(&cmdargs).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_thresholds() {
+        let args = vec![
+            String::from("-Warning"),
+            String::from("80:90"),
+            String::from("-CriticalFree"),
+            String::from("10GB:"),
+            String::from("-Include"),
+            String::from("1:2:3"),
+        ];
+        let cmdargs = CommandArguments::try_from(args.as_slice()).unwrap();
+        assert!(cmdargs.check_thresholds().is_ok());
+        let args = vec![String::from("-Critical"), String::from("'@10:2O'")];
+        let cmdargs = CommandArguments::try_from(args.as_slice()).unwrap();
+        assert_eq!(
+            cmdargs.check_thresholds().unwrap_err().to_string(),
+            "invalid threshold '@10:2O' for parameter '-Critical' (unknown unit 'O' in '2O')"
+        );
+    }
+
+    #[test]
+    fn test_resolve_secrets() {
+        std::env::set_var("I4W_CALLAPI_TEST_PASSWORD", "pw");
+        let args = vec![
+            String::from("-Password"),
+            String::from("secret:env:I4W_CALLAPI_TEST_PASSWORD"),
+        ];
+        let cmdargs = CommandArguments::try_from(args.as_slice()).unwrap();
+        let resolved = cmdargs.resolve_secrets(&SecretResolver::new(None)).unwrap();
+        assert_eq!(
+            serde_json::to_string(&resolved).unwrap(),
+            r#"{"Password":"(ConvertTo-IcingaSecureString 'pw')"}"#
+        );
+        // the bound arguments keep the reference only
+        assert_eq!(
+            cmdargs.0.get("Password").unwrap(),
+            &CliArgument::String("secret:env:I4W_CALLAPI_TEST_PASSWORD".to_owned())
+        );
+    }
+
     #[test]
     fn test_serialize_arglist() {
         let mut outer = HashMap::new();
@@ -233,6 +662,18 @@ mod tests {
         assert_eq!(value.perfdata, Perfdata::None(EmptyObject::new()));
     }
 
+    #[test]
+    fn test_serialize_checker_result() {
+        for data in &[
+            r#"{"exitcode":2,"checkresult":"[CRITICAL] Check package \"Bar\"","perfdata":"'baz'=158;;"}"#,
+            r#"{"exitcode":0,"checkresult":"[OK]","perfdata":["'baz'=158;;","'qux'=158;;"]}"#,
+            r#"{"exitcode":{},"checkresult":"","perfdata":{}}"#,
+        ] {
+            let value: CheckerResult = serde_json::from_str(data).unwrap();
+            assert_eq!(serde_json::to_string(&value).unwrap(), *data);
+        }
+    }
+
     #[test]
     fn test_format_perfdata() {
         assert_eq!(
@@ -244,4 +685,156 @@ mod tests {
             "'baz'=158;; 'qux'=158;;"
         );
     }
+
+    #[test]
+    fn test_format_checker_result() {
+        let golden = [
+            (
+                "[OK] Check package \"CPU Load\"",
+                Perfdata::Single(String::from("'core_total'=4%;80;90")),
+                "[OK] Check package \"CPU Load\" | 'core_total'=4%;80;90",
+            ),
+            (
+                "[WARNING] Check package \"CPU Load\"\r\n\\_ [WARNING] Core #0: 85%\r\n\\_ [OK] Core #1: 4%",
+                Perfdata::Multiple(vec![
+                    String::from("'core_0'=85%;80;90"),
+                    String::from("'core_1'=4%;80;90"),
+                ]),
+                "[WARNING] Check package \"CPU Load\" | 'core_0'=85%;80;90 'core_1'=4%;80;90\n\\_ [WARNING] Core #0: 85%\n\\_ [OK] Core #1: 4%",
+            ),
+            (
+                "[OK] Check package \"CPU Load\"\r\n\\_ [OK] Core #0: 4%\r\n",
+                Perfdata::Single(String::from("'core_0'=4%")),
+                "[OK] Check package \"CPU Load\" | 'core_0'=4%\n\\_ [OK] Core #0: 4%",
+            ),
+            (
+                "[OK] Check package \"Services\"\n\\_ [OK] wuauserv Running\n",
+                Perfdata::None(EmptyObject::new()),
+                "[OK] Check package \"Services\"\n\\_ [OK] wuauserv Running",
+            ),
+            (
+                "[OK] Check package \"Services\"\n",
+                Perfdata::Multiple(Vec::new()),
+                "[OK] Check package \"Services\"",
+            ),
+            (
+                "",
+                Perfdata::Single(String::from("'baz'=158;;")),
+                " | 'baz'=158;;",
+            ),
+        ];
+        for (checkresult, perfdata, expected) in golden {
+            let result = CheckerResult {
+                exitcode: Exitcode::Executed(0),
+                checkresult: String::from(checkresult),
+                perfdata,
+            };
+            assert_eq!(result.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_perfdata_points() {
+        let perfdata = Perfdata::Multiple(vec![
+            String::from("'baz'=158;; 'quux'=U"),
+            String::from("'qux'=1.5s;1;2"),
+        ]);
+        let labels = perfdata
+            .points()
+            .unwrap()
+            .into_iter()
+            .map(|point| point.label)
+            .collect::<Vec<String>>();
+        assert_eq!(labels, vec!["baz", "quux", "qux"]);
+        assert_eq!(perfdata.to_string(), "'baz'=158;; 'quux'=U 'qux'=1.5s;1;2");
+        assert_eq!(Perfdata::None(EmptyObject::new()).points().unwrap(), vec![]);
+        assert!(Perfdata::Single(String::from("baz")).points().is_err());
+    }
+
+    #[test]
+    fn test_normalize_perfdata() {
+        let result = CheckerResult {
+            exitcode: Exitcode::Executed(0),
+            checkresult: String::from("[OK] Check package \"Bar\""),
+            perfdata: Perfdata::Single(String::from("'baz'=158;;\r\nfree space=1GB")),
+        };
+        let (perfdata, fixes) = result.perfdata.normalize();
+        assert_eq!(perfdata.to_string(), "'baz'=158;; 'free space'=1GB");
+        assert_eq!(fixes.len(), 2);
+        let (perfdata, fixes) = Perfdata::Multiple(vec![String::from("'a b'")]).normalize();
+        assert!(!perfdata.valid());
+        assert_eq!(fixes, vec!["dropped 'a b' without value"]);
+    }
+
+    #[test]
+    fn test_override_thresholds() {
+        let result = |exitcode: i32| CheckerResult {
+            exitcode: Exitcode::Executed(exitcode),
+            checkresult: String::from("[WARNING] Check package \"CPU Load\""),
+            perfdata: Perfdata::Single(String::from("'core_0'=85%;80;90 'core_total'=50%;80;90")),
+        };
+        let overrides = [ThresholdOverride::parse("core_0=90;95").unwrap()];
+        let relaxed = result(1).override_thresholds(&overrides).unwrap();
+        assert_eq!(relaxed.exitcode, Exitcode::Executed(0));
+        assert_eq!(
+            relaxed.to_string(),
+            "[WARNING] Check package \"CPU Load\" | core_0=85%;90;95 'core_total'=50%;80;90"
+        );
+        // the plugin found something worse than its perfdata tells
+        let kept = result(2).override_thresholds(&overrides).unwrap();
+        assert_eq!(kept.exitcode, Exitcode::Executed(2));
+        let overrides = [ThresholdOverride::parse("core_total=30:40").unwrap()];
+        let stricter = result(1).override_thresholds(&overrides).unwrap();
+        assert_eq!(stricter.exitcode, Exitcode::Executed(2));
+        let unknown = result(3).override_thresholds(&overrides).unwrap();
+        assert_eq!(unknown.exitcode, Exitcode::Executed(3));
+    }
+
+    #[test]
+    fn test_apply_state_rules() {
+        let rules = StateRules::new(&["warning=ok", "not-executed=critical"], &[]).unwrap();
+        let result = CheckerResult {
+            exitcode: Exitcode::Executed(1),
+            checkresult: String::from("[WARNING] Check package \"CPU Load\""),
+            perfdata: Perfdata::Single(String::from("'core_total'=85%;80;90")),
+        }
+        .apply_state_rules(&rules);
+        assert_eq!(result.exitcode, Exitcode::Executed(0));
+        assert_eq!(
+            result.to_string(),
+            "[WARNING] Check package \"CPU Load\" | 'core_total'=85%;80;90\nstate changed from WARNING to OK by rule 'warning=ok'"
+        );
+        let result = CheckerResult {
+            exitcode: Exitcode::NotExecuted(EmptyObject::new()),
+            checkresult: String::new(),
+            perfdata: Perfdata::None(EmptyObject::new()),
+        }
+        .apply_state_rules(&rules);
+        assert_eq!(result.exitcode, Exitcode::Executed(2));
+    }
+
+    #[test]
+    fn test_perfdata_rules() {
+        let result = CheckerResult {
+            exitcode: Exitcode::Executed(0),
+            checkresult: String::from("[OK] Check package \"CPU Load\""),
+            perfdata: Perfdata::Single(String::from("'core_0'=3% 'core_total'=4%")),
+        };
+        let rules = PerfdataRules::new(&["total"], &[], &[]).unwrap();
+        let result = CheckerResult {
+            perfdata: result.perfdata.apply_rules(&rules).unwrap(),
+            ..result
+        };
+        assert_eq!(
+            result.to_string(),
+            "[OK] Check package \"CPU Load\" | 'core_total'=4%"
+        );
+        let rules = PerfdataRules::new(&["nothing"], &[], &[]).unwrap();
+        assert_eq!(
+            result.perfdata.normalize_units().unwrap().to_string(),
+            "'core_total'=4%"
+        );
+        let perfdata = result.perfdata.apply_rules(&rules).unwrap();
+        assert!(!perfdata.valid());
+    }
 }
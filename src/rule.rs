@@ -0,0 +1,42 @@
+// Errors of the rules given on the command line to rewrite perfdata and states, shared along
+// with compiling the regular expressions in them.
+
+use regex::Regex;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub struct RuleError {
+    // what the rule applies to, like perfdata or state
+    pub kind: &'static str,
+    pub rule: String,
+    pub reason: String,
+}
+
+impl RuleError {
+    pub fn new(kind: &'static str, rule: &str, reason: &str) -> Self {
+        RuleError {
+            kind,
+            rule: rule.to_owned(),
+            reason: reason.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid {} rule '{}' ({})",
+            self.kind, self.rule, self.reason
+        )
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+// The error of the regex crate spans several lines pointing at the bad part of the pattern, only
+// its last line with the reason is kept.
+pub fn compile(kind: &'static str, rule: &str, pattern: &str) -> Result<Regex, RuleError> {
+    Regex::new(pattern)
+        .map_err(|e| RuleError::new(kind, rule, e.to_string().lines().last().unwrap_or_default()))
+}
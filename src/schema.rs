@@ -0,0 +1,430 @@
+use crate::ps::{CastType, CliArgument};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterType {
+    String,
+    Int,
+    Float,
+    Switch,
+    Array,
+    Hashtable,
+    DateTime,
+    TimeSpan,
+}
+
+impl fmt::Display for ParameterType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParameterType::String => write!(f, "string"),
+            ParameterType::Int => write!(f, "int"),
+            ParameterType::Float => write!(f, "float"),
+            ParameterType::Switch => write!(f, "switch"),
+            ParameterType::Array => write!(f, "array"),
+            ParameterType::Hashtable => write!(f, "hashtable"),
+            ParameterType::DateTime => write!(f, "datetime"),
+            ParameterType::TimeSpan => write!(f, "timespan"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct ParameterSchema {
+    #[serde(rename = "type")]
+    pub parameter_type: ParameterType,
+    // element type of an array parameter, elements are left as they are if omitted
+    #[serde(default)]
+    pub items: Option<ParameterType>,
+    #[serde(default, rename = "enum")]
+    pub allowed_values: Vec<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    // position for binding bare values like PowerShell's [Parameter(Position = n)]
+    #[serde(default)]
+    pub position: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct CommandSchema {
+    pub parameters: IndexMap<String, ParameterSchema>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SchemaError {
+    UnknownParameter {
+        parameter: String,
+        suggestion: Option<String>,
+    },
+    AmbiguousParameter {
+        parameter: String,
+        candidates: Vec<String>,
+    },
+    // by name and alias, or by name and position
+    DuplicateParameter {
+        parameter: String,
+    },
+    InvalidType {
+        parameter: String,
+        expected: String,
+    },
+    NotAllowed {
+        parameter: String,
+        allowed_values: Vec<String>,
+    },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::UnknownParameter {
+                parameter,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "unknown parameter '-{}', did you mean '-{}'?",
+                parameter, suggestion
+            ),
+            SchemaError::UnknownParameter {
+                parameter,
+                suggestion: None,
+            } => write!(f, "unknown parameter '-{}'", parameter),
+            SchemaError::AmbiguousParameter {
+                parameter,
+                candidates,
+            } => write!(
+                f,
+                "parameter '-{}' is ambiguous, possible matches: -{}",
+                parameter,
+                candidates.join(", -")
+            ),
+            SchemaError::DuplicateParameter { parameter } => {
+                write!(f, "parameter '-{}' is specified more than once", parameter)
+            }
+            SchemaError::InvalidType {
+                parameter,
+                expected,
+            } => write!(
+                f,
+                "invalid value for parameter '-{}' (expected {})",
+                parameter, expected
+            ),
+            SchemaError::NotAllowed {
+                parameter,
+                allowed_values,
+            } => write!(
+                f,
+                "invalid value for parameter '-{}' (allowed values: {})",
+                parameter,
+                allowed_values.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl CommandSchema {
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("failed to open schema file '{}' ({})", path, e))?;
+        let schema = serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|e| format!("failed to read schema file '{}' ({})", path, e))?;
+        Ok(schema)
+    }
+
+    // Like PowerShell, names and aliases are case insensitive and may be abbreviated as long as
+    // the abbreviation is unambiguous.
+    pub fn resolve(&self, name: &str) -> Result<(&str, &ParameterSchema), SchemaError> {
+        let exact_match = self.parameters.iter().find(|(parameter_name, parameter)| {
+            parameter_name.eq_ignore_ascii_case(name)
+                || parameter
+                    .aliases
+                    .iter()
+                    .any(|alias| alias.eq_ignore_ascii_case(name))
+        });
+        if let Some((parameter_name, parameter)) = exact_match {
+            return Ok((parameter_name, parameter));
+        }
+
+        let lowercase_name = name.to_lowercase();
+        let prefix_matches: Vec<(&String, &ParameterSchema)> = self
+            .parameters
+            .iter()
+            .filter(|(parameter_name, _)| {
+                parameter_name.to_lowercase().starts_with(&lowercase_name)
+            })
+            .collect();
+        match prefix_matches.as_slice() {
+            [(parameter_name, parameter)] => Ok((parameter_name, parameter)),
+            [] => Err(SchemaError::UnknownParameter {
+                parameter: name.to_owned(),
+                suggestion: self.suggest(name),
+            }),
+            _ => Err(SchemaError::AmbiguousParameter {
+                parameter: name.to_owned(),
+                candidates: prefix_matches
+                    .iter()
+                    .map(|(parameter_name, _)| (*parameter_name).clone())
+                    .collect(),
+            }),
+        }
+    }
+
+    pub fn switch_parameters(&self) -> Vec<String> {
+        self.parameters
+            .iter()
+            .filter(|(_, parameter)| parameter.parameter_type == ParameterType::Switch)
+            .map(|(parameter_name, _)| parameter_name.clone())
+            .collect()
+    }
+
+    // Parameters taking strings, also as array elements.
+    pub fn string_parameters(&self) -> Vec<String> {
+        self.parameters
+            .iter()
+            .filter(|(_, parameter)| {
+                parameter.parameter_type == ParameterType::String
+                    || parameter.items == Some(ParameterType::String)
+            })
+            .map(|(parameter_name, _)| parameter_name.clone())
+            .collect()
+    }
+
+    pub fn positional_parameters(&self) -> Vec<String> {
+        let mut positional_parameters: Vec<(usize, &String)> = self
+            .parameters
+            .iter()
+            .filter_map(|(parameter_name, parameter)| {
+                parameter
+                    .position
+                    .map(|position| (position, parameter_name))
+            })
+            .collect();
+        positional_parameters.sort();
+        positional_parameters
+            .into_iter()
+            .map(|(_, parameter_name)| parameter_name.clone())
+            .collect()
+    }
+
+    fn suggest(&self, name: &str) -> Option<String> {
+        let lowercase_name = name.to_lowercase();
+        self.parameters
+            .keys()
+            .map(|parameter_name| {
+                let confidence =
+                    strsim::jaro_winkler(&lowercase_name, &parameter_name.to_lowercase());
+                (confidence, parameter_name)
+            })
+            .filter(|(confidence, _)| *confidence > 0.8)
+            .max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap())
+            .map(|(_, parameter_name)| parameter_name.clone())
+    }
+}
+
+impl ParameterSchema {
+    pub fn coerce(&self, name: &str, value: CliArgument) -> Result<CliArgument, SchemaError> {
+        // raw JSON is the escape hatch for values the schema can't describe
+        if let CliArgument::Json(_) = value {
+            return Ok(value);
+        }
+        let coerced = match self.items {
+            Some(items) if self.parameter_type == ParameterType::Array => {
+                match coerce_to(ParameterType::Array, value) {
+                    Some(CliArgument::Array(elements)) => elements
+                        .into_iter()
+                        .map(|element| coerce_to(items, element))
+                        .collect::<Option<Vec<CliArgument>>>()
+                        .map(CliArgument::Array),
+                    _ => None,
+                }
+            }
+            _ => coerce_to(self.parameter_type, value),
+        }
+        .ok_or_else(|| SchemaError::InvalidType {
+            parameter: name.to_owned(),
+            expected: match self.items {
+                Some(items) if self.parameter_type == ParameterType::Array => {
+                    format!("array of {}", items)
+                }
+                _ => self.parameter_type.to_string(),
+            },
+        })?;
+
+        if self.allowed_values.is_empty() || self.is_allowed(&coerced) {
+            Ok(coerced)
+        } else {
+            Err(SchemaError::NotAllowed {
+                parameter: name.to_owned(),
+                allowed_values: self.allowed_values.clone(),
+            })
+        }
+    }
+
+    // Compares case insensitive like PowerShell's ValidateSet does.
+    fn is_allowed(&self, value: &CliArgument) -> bool {
+        match value {
+            CliArgument::Array(elements) => elements.iter().all(|element| self.is_allowed(element)),
+            CliArgument::Hashtable(_) | CliArgument::Json(_) => false,
+            skalar => match CastType::String.apply(skalar.clone()) {
+                Ok(CliArgument::String(string)) => self
+                    .allowed_values
+                    .iter()
+                    .any(|allowed_value| allowed_value.eq_ignore_ascii_case(&string)),
+                _ => false,
+            },
+        }
+    }
+}
+
+fn coerce_to(parameter_type: ParameterType, value: CliArgument) -> Option<CliArgument> {
+    let cast_type = match parameter_type {
+        ParameterType::String => CastType::String,
+        ParameterType::Int => CastType::Int,
+        ParameterType::Float => CastType::Double,
+        ParameterType::Switch => CastType::Bool,
+        ParameterType::Array => CastType::Array,
+        ParameterType::DateTime => CastType::DateTime,
+        ParameterType::TimeSpan => CastType::TimeSpan,
+        ParameterType::Hashtable => {
+            return match value {
+                CliArgument::Hashtable(_) => Some(value),
+                _ => None,
+            }
+        }
+    };
+    cast_type.apply(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandSchema, ParameterType, SchemaError};
+    use crate::ps::{from_str, CliArgument, Number};
+
+    fn schema() -> CommandSchema {
+        let data = r#"{
+            "parameters": {
+                "Include": { "type": "array", "items": "string", "aliases": ["Service"], "position": 0 },
+                "Status": { "type": "string", "enum": ["Running", "Stopped"], "position": 1 },
+                "Warning": { "type": "int" },
+                "WarningFree": { "type": "float" },
+                "NoPerfData": { "type": "switch" },
+                "Filter": { "type": "hashtable" },
+                "After": { "type": "datetime" },
+                "Timespan": { "type": "timespan" }
+            }
+        }"#;
+        serde_json::from_str(data).unwrap()
+    }
+
+    #[test]
+    fn test_deserialize_schema() {
+        let schema = schema();
+        assert_eq!(schema.parameters.len(), 8);
+        let include = schema.parameters.get("Include").unwrap();
+        assert_eq!(include.parameter_type, ParameterType::Array);
+        assert_eq!(include.items, Some(ParameterType::String));
+        assert_eq!(include.aliases, vec!["Service".to_owned()]);
+        assert_eq!(
+            schema.positional_parameters(),
+            vec!["Include".to_owned(), "Status".to_owned()]
+        );
+        assert_eq!(schema.switch_parameters(), vec!["NoPerfData".to_owned()]);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let schema = schema();
+        assert_eq!(schema.resolve("include").unwrap().0, "Include");
+        assert_eq!(schema.resolve("SERVICE").unwrap().0, "Include");
+        assert_eq!(schema.resolve("Stat").unwrap().0, "Status");
+        assert_eq!(schema.resolve("Warning").unwrap().0, "Warning");
+        assert_eq!(
+            schema.resolve("Warn").unwrap_err(),
+            SchemaError::AmbiguousParameter {
+                parameter: "Warn".to_owned(),
+                candidates: vec!["Warning".to_owned(), "WarningFree".to_owned()],
+            }
+        );
+        let err = schema.resolve("Incluce").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown parameter '-Incluce', did you mean '-Include'?"
+        );
+        assert_eq!(
+            schema.resolve("Foo").unwrap_err(),
+            SchemaError::UnknownParameter {
+                parameter: "Foo".to_owned(),
+                suggestion: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_coerce() {
+        let schema = schema();
+        let coerce = |name: &str, input: &str| {
+            let (name, parameter) = schema.resolve(name).unwrap();
+            parameter.coerce(name, from_str(input).unwrap())
+        };
+        assert_eq!(
+            coerce("Include", "1234").unwrap(),
+            CliArgument::Array(vec![CliArgument::String("1234".to_owned())])
+        );
+        assert_eq!(
+            coerce("Include", "foo,12").unwrap(),
+            CliArgument::Array(vec![
+                CliArgument::String("foo".to_owned()),
+                CliArgument::String("12".to_owned())
+            ])
+        );
+        assert_eq!(
+            coerce("Warning", "'80'").unwrap(),
+            CliArgument::Number(Number::PosInt(80))
+        );
+        assert_eq!(
+            coerce("WarningFree", "5").unwrap(),
+            CliArgument::Number(Number::Float(5.0))
+        );
+        assert_eq!(
+            coerce("NoPerfData", "$True").unwrap(),
+            CliArgument::Bool(true)
+        );
+        assert_eq!(
+            coerce("Status", "running").unwrap(),
+            CliArgument::String("running".to_owned())
+        );
+        assert!(matches!(
+            coerce("Filter", "@{ Name = 'foo' }").unwrap(),
+            CliArgument::Hashtable(_)
+        ));
+        assert_eq!(
+            coerce("Warning", "foo").unwrap_err().to_string(),
+            "invalid value for parameter '-Warning' (expected int)"
+        );
+        assert_eq!(
+            coerce("Include", "[foo,[bar]]").unwrap_err().to_string(),
+            "invalid value for parameter '-Include' (expected array of string)"
+        );
+        assert_eq!(
+            coerce("Status", "Paused").unwrap_err().to_string(),
+            "invalid value for parameter '-Status' (allowed values: Running, Stopped)"
+        );
+        assert!(coerce("Filter", "foo").is_err());
+        assert_eq!(
+            coerce("After", "'2026-10-01 00:00'").unwrap(),
+            CliArgument::String("2026-10-01T00:00:00".to_owned())
+        );
+        assert_eq!(
+            coerce("Timespan", "1d").unwrap(),
+            CliArgument::String("1.00:00:00".to_owned())
+        );
+        assert_eq!(
+            coerce("Timespan", "tomorrow").unwrap_err().to_string(),
+            "invalid value for parameter '-Timespan' (expected timespan)"
+        );
+    }
+}
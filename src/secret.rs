@@ -0,0 +1,209 @@
+use crate::ps::CliArgument;
+use std::collections::HashMap;
+use std::fmt;
+
+const SECRET_PREFIX: &str = "secret:";
+
+#[derive(Debug, PartialEq)]
+pub enum SecretSource<'a> {
+    Env(&'a str),
+    File(&'a str),
+    Credential(&'a str),
+}
+
+impl<'a> SecretSource<'a> {
+    pub fn parse(reference: &'a str) -> Option<Result<SecretSource<'a>, SecretError>> {
+        let source = reference.strip_prefix(SECRET_PREFIX)?;
+        Some(if let Some(name) = source.strip_prefix("env:") {
+            Ok(SecretSource::Env(name))
+        } else if let Some(path) = source.strip_prefix("file:") {
+            Ok(SecretSource::File(path))
+        } else if let Some(name) = source.strip_prefix("cred:") {
+            Ok(SecretSource::Credential(name))
+        } else {
+            Err(SecretError {
+                reference: reference.to_owned(),
+                reason: String::from("unknown secret source, expected env, file or cred"),
+            })
+        })
+    }
+}
+
+// Never carries the secret value itself, only the reference and what went wrong.
+#[derive(Debug, PartialEq)]
+pub struct SecretError {
+    pub reference: String,
+    pub reason: String,
+}
+
+impl fmt::Display for SecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to resolve secret '{}' ({})",
+            self.reference, self.reason
+        )
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+pub struct SecretResolver {
+    credentials_file: Option<String>,
+}
+
+impl SecretResolver {
+    pub fn new(credentials_file: Option<&str>) -> Self {
+        SecretResolver {
+            credentials_file: credentials_file.map(String::from),
+        }
+    }
+
+    // Replaces secret references anywhere in the argument by the expression the daemon
+    // evaluates to a SecureString.
+    pub fn resolve_argument(&self, argument: &CliArgument) -> Result<CliArgument, SecretError> {
+        match argument {
+            CliArgument::String(string) => match SecretSource::parse(string) {
+                Some(source) => {
+                    let secret = self.read(string, source?)?;
+                    Ok(CliArgument::String(format!(
+                        "(ConvertTo-IcingaSecureString '{}')",
+                        secret.replace('\'', "''")
+                    )))
+                }
+                None => Ok(argument.clone()),
+            },
+            CliArgument::Array(array) => array
+                .iter()
+                .map(|element| self.resolve_argument(element))
+                .collect::<Result<Vec<CliArgument>, SecretError>>()
+                .map(CliArgument::Array),
+            CliArgument::Hashtable(hashtable) => hashtable
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), self.resolve_argument(value)?)))
+                .collect::<Result<_, SecretError>>()
+                .map(CliArgument::Hashtable),
+            _ => Ok(argument.clone()),
+        }
+    }
+
+    fn read(&self, reference: &str, source: SecretSource) -> Result<String, SecretError> {
+        let error = |reason: String| SecretError {
+            reference: reference.to_owned(),
+            reason,
+        };
+        match source {
+            SecretSource::Env(name) => std::env::var(name)
+                .map_err(|_| error(format!("environment variable '{}' is not set", name))),
+            SecretSource::File(path) => std::fs::read_to_string(path)
+                .map(|content| content.trim_end_matches(&['\r', '\n'][..]).to_owned())
+                .map_err(|e| error(format!("failed to read file '{}': {}", path, e))),
+            SecretSource::Credential(name) => {
+                let credentials_file = self
+                    .credentials_file
+                    .as_ref()
+                    .ok_or_else(|| error(String::from("no credentials file given")))?;
+                let content = std::fs::read_to_string(credentials_file).map_err(|e| {
+                    error(format!(
+                        "failed to read credentials file '{}': {}",
+                        credentials_file, e
+                    ))
+                })?;
+                // serde_json error messages may quote the content, only report the position
+                let mut credentials: HashMap<String, String> = serde_json::from_str(&content)
+                    .map_err(|e| {
+                        error(format!(
+                            "invalid credentials file '{}' at line {} column {}",
+                            credentials_file,
+                            e.line(),
+                            e.column()
+                        ))
+                    })?;
+                credentials
+                    .remove(name)
+                    .ok_or_else(|| error(format!("no credential '{}' found", name)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SecretResolver, SecretSource};
+    use crate::ps::CliArgument;
+    use crate::test_util::TempFile;
+
+    #[test]
+    fn test_parse_source() {
+        assert!(SecretSource::parse("foo").is_none());
+        assert_eq!(
+            SecretSource::parse("secret:env:PW").unwrap(),
+            Ok(SecretSource::Env("PW"))
+        );
+        assert_eq!(
+            SecretSource::parse("secret:file:C:\\pw.txt").unwrap(),
+            Ok(SecretSource::File("C:\\pw.txt"))
+        );
+        assert_eq!(
+            SecretSource::parse("secret:cred:sql").unwrap(),
+            Ok(SecretSource::Credential("sql"))
+        );
+        assert!(SecretSource::parse("secret:vault:sql").unwrap().is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        std::env::set_var("I4W_CALLAPI_TEST_SECRET", "it's secret");
+        let resolver = SecretResolver::new(None);
+        let argument = CliArgument::Array(vec![
+            CliArgument::String("secret:env:I4W_CALLAPI_TEST_SECRET".to_owned()),
+            CliArgument::String("plain".to_owned()),
+        ]);
+        assert_eq!(
+            resolver.resolve_argument(&argument).unwrap(),
+            CliArgument::Array(vec![
+                CliArgument::String("(ConvertTo-IcingaSecureString 'it''s secret')".to_owned()),
+                CliArgument::String("plain".to_owned()),
+            ])
+        );
+
+        let file = TempFile::new("secret_file", "from file\r\n");
+        let path = file.path();
+        let argument = CliArgument::String(format!("secret:file:{}", path));
+        assert_eq!(
+            resolver.resolve_argument(&argument).unwrap(),
+            CliArgument::String("(ConvertTo-IcingaSecureString 'from file')".to_owned())
+        );
+
+        let file = TempFile::new("credentials", r#"{"sql": "from credentials"}"#);
+        let path = file.path();
+        let resolver = SecretResolver::new(Some(path));
+        let argument = CliArgument::String("secret:cred:sql".to_owned());
+        assert_eq!(
+            resolver.resolve_argument(&argument).unwrap(),
+            CliArgument::String("(ConvertTo-IcingaSecureString 'from credentials')".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_errors_hide_secret() {
+        let file = TempFile::new("bad_credentials", r#"{"sql": topsecret}"#);
+        let path = file.path();
+        let resolver = SecretResolver::new(Some(path));
+        let err = resolver
+            .resolve_argument(&CliArgument::String("secret:cred:sql".to_owned()))
+            .unwrap_err();
+        assert!(!err.to_string().contains("topsecret"));
+        assert!(err.to_string().contains("line 1 column"));
+
+        let err = SecretResolver::new(None)
+            .resolve_argument(&CliArgument::String(
+                "secret:env:I4W_CALLAPI_TEST_UNSET".to_owned(),
+            ))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed to resolve secret 'secret:env:I4W_CALLAPI_TEST_UNSET' (environment variable 'I4W_CALLAPI_TEST_UNSET' is not set)"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use crate::input::{self, InputFormat};
+use crate::ps::{self, CliArgument, ParameterBinderToken};
+use crate::schema::{CommandSchema, ParameterType};
+use indexmap::IndexMap;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub struct SplatError {
+    pub path: String,
+    pub reason: String,
+}
+
+impl fmt::Display for SplatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to splat '{}' ({})", self.path, self.reason)
+    }
+}
+
+impl std::error::Error for SplatError {}
+
+fn extension(path: &str) -> Option<String> {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase)
+}
+
+// A forwarded argument like @C:\checks\cpu.psd1. Anything else starting with @, like the
+// threshold @10:20 or an array @( or hashtable @{ literal, is left to the parser.
+fn splat_path(arg: &str) -> Option<&str> {
+    let path = arg.strip_prefix('@')?;
+    match extension(path).as_deref() {
+        Some("psd1") | Some("json") if !path.starts_with(&['(', '{'][..]) => Some(path),
+        _ => None,
+    }
+}
+
+// Whether the argument after this one is its value. Without a schema, every parameter might
+// take one.
+fn takes_value(arg: &str, schema: Option<&CommandSchema>) -> bool {
+    arg.is_parameter_name()
+        && !schema
+            .and_then(|schema| schema.resolve(&arg.as_parameter_name()).ok())
+            .is_some_and(|(_, parameter)| parameter.parameter_type == ParameterType::Switch)
+}
+
+// The name a parameter is bound to, so aliases and abbreviations of a parameter are the same.
+fn bound_name(param_name: &str, schema: Option<&CommandSchema>) -> String {
+    schema
+        .and_then(|schema| schema.resolve(param_name).ok())
+        .map_or(param_name, |(param_name, _)| param_name)
+        .to_lowercase()
+}
+
+// Replaces splat arguments by the parameters loaded from the files. Later files replace
+// parameters of earlier ones, explicitly passed parameters replace splatted ones. The splatted
+// parameters are put in front of all other arguments. Only arguments where a parameter name
+// could stand are splatted, never the value of a parameter.
+pub fn expand<T: AsRef<str>>(
+    args: &[T],
+    schema: Option<&CommandSchema>,
+) -> Result<Vec<String>, SplatError> {
+    let mut splatted_parameters: IndexMap<String, (String, CliArgument)> = IndexMap::new();
+    let mut explicit_args = Vec::new();
+    let mut explicit_names = Vec::new();
+    let mut is_value = false;
+    for arg in args {
+        let arg = arg.as_ref();
+        match splat_path(arg) {
+            Some(path) if !is_value => {
+                for (param_name, param_value) in load(path)? {
+                    let name = bound_name(&param_name, schema);
+                    splatted_parameters.shift_remove(&name);
+                    splatted_parameters.insert(name, (param_name, param_value));
+                }
+                is_value = false;
+            }
+            _ => {
+                if !is_value && arg.is_parameter_name() {
+                    explicit_names.push(bound_name(&arg.as_parameter_name(), schema));
+                }
+                explicit_args.push(arg.to_owned());
+                is_value = takes_value(arg, schema);
+            }
+        }
+    }
+    let mut expanded_args = Vec::new();
+    for (name, (param_name, param_value)) in &splatted_parameters {
+        if explicit_names.contains(name) {
+            continue;
+        }
+        expanded_args.push(format!("-{}", param_name));
+        if *param_value != CliArgument::Bool(true) {
+            expanded_args.push(ps::to_string(param_value));
+        }
+    }
+    expanded_args.append(&mut explicit_args);
+    Ok(expanded_args)
+}
+
+pub fn load(path: &str) -> Result<IndexMap<String, CliArgument>, SplatError> {
+    let error = |reason: String| SplatError {
+        path: path.to_owned(),
+        reason,
+    };
+    let content = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    match extension(path).as_deref() {
+        Some("psd1") => match ps::from_str(content.trim()) {
+            Ok(CliArgument::Hashtable(hashtable)) => input::check_parameter_names(hashtable),
+            Ok(_) => Err(String::from("expected a hashtable @{ ... }")),
+            Err(e) => Err(e.to_string()),
+        },
+        Some("json") => input::parse(&content, InputFormat::Json),
+        _ => Err(String::from("expected a .psd1 or .json file")),
+    }
+    .map_err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, load};
+    use crate::ps::{CliArgument, Number};
+    use crate::restapiv1::CommandArguments;
+    use crate::schema::CommandSchema;
+    use crate::test_util::TempFile;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_load_psd1() {
+        let file = TempFile::new(
+            "splat.psd1",
+            "@{\r\n    Warning = 80\r\n    Include = @('a', 'b')\r\n    NoPerfData = $true\r\n}\r\n",
+        );
+        let path = file.path();
+        let parameters = load(path).unwrap();
+        assert_eq!(
+            parameters.keys().collect::<Vec<&String>>(),
+            vec!["Warning", "Include", "NoPerfData"]
+        );
+        assert_eq!(
+            parameters.get("Warning").unwrap(),
+            &CliArgument::Number(Number::PosInt(80))
+        );
+        assert_eq!(
+            parameters.get("NoPerfData").unwrap(),
+            &CliArgument::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_load_json() {
+        let file = TempFile::new(
+            "splat.json",
+            r#"{"Warning": 80, "Critical": -1.5, "Include": ["a", "b"], "NoPerfData": false}"#,
+        );
+        let path = file.path();
+        let parameters = load(path).unwrap();
+        assert_eq!(
+            serde_json::to_string(&parameters).unwrap(),
+            r#"{"Warning":80,"Critical":-1.5,"Include":["a","b"],"NoPerfData":false}"#
+        );
+
+        let file = TempFile::new("splat_null.json", r#"{"Warning": null}"#);
+        let path = file.path();
+        assert_eq!(
+            load(path).unwrap()["Warning"],
+            CliArgument::Json(serde_json::Value::Null)
+        );
+        let file = TempFile::new("splat_invalid.json", r#"{"Warning": secret}"#);
+        let path = file.path();
+        assert_eq!(
+            load(path).unwrap_err().reason,
+            "invalid JSON at line 1 column 13"
+        );
+        let file = TempFile::new("splat_name.json", r#"{"1st": 1}"#);
+        let path = file.path();
+        assert_eq!(
+            load(path).unwrap_err().reason,
+            "invalid parameter name '1st'"
+        );
+        assert!(load("splat.txt").is_err());
+    }
+
+    #[test]
+    fn test_expand() {
+        let file = TempFile::new(
+            "expand.psd1",
+            "@{ Warning = 80; Include = 'a b'; NoPerfData = $true }",
+        );
+        let path = file.path();
+        let args = vec![
+            "-Warning".to_owned(),
+            "90".to_owned(),
+            format!("@{}", path),
+            "@(1,2)".to_owned(),
+        ];
+        assert_eq!(
+            expand(&args, None).unwrap(),
+            vec![
+                "-Include",
+                "'a b'",
+                "-NoPerfData",
+                "-Warning",
+                "90",
+                "@(1,2)"
+            ]
+        );
+
+        let args = vec![format!("@{}", path), "-Warning".to_owned(), "90".to_owned()];
+        let cmdargs = CommandArguments::try_from(expand(&args, None).unwrap().as_slice()).unwrap();
+        assert_eq!(
+            serde_json::to_string(&cmdargs).unwrap(),
+            r#"{"Include":"a b","NoPerfData":true,"Warning":90}"#
+        );
+
+        // with a schema, explicit parameters replace splatted ones also by alias, while passing
+        // a parameter twice explicitly is still an error
+        let schema: CommandSchema = serde_json::from_str(
+            r#"{"parameters": {
+                "Warning": {"type": "int", "aliases": ["Warn"]},
+                "Include": {"type": "array", "items": "string"},
+                "NoPerfData": {"type": "switch"}
+            }}"#,
+        )
+        .unwrap();
+        let other_file = TempFile::new("expand_other.json", r#"{"include": ["c"]}"#);
+        let args = vec![
+            format!("@{}", path),
+            format!("@{}", other_file.path()),
+            "-Warn".to_owned(),
+            "90".to_owned(),
+        ];
+        let expanded = expand(&args, Some(&schema)).unwrap();
+        let cmdargs = CommandArguments::bind(&expanded, &schema).unwrap();
+        assert_eq!(
+            serde_json::to_string(&cmdargs).unwrap(),
+            r#"{"NoPerfData":true,"Include":["c"],"Warning":90}"#
+        );
+        let args = vec![
+            format!("@{}", path),
+            "-Warning".to_owned(),
+            "90".to_owned(),
+            "-Warn".to_owned(),
+            "95".to_owned(),
+        ];
+        let expanded = expand(&args, Some(&schema)).unwrap();
+        assert_eq!(
+            CommandArguments::bind(&expanded, &schema)
+                .unwrap_err()
+                .to_string(),
+            "parameter '-Warning' is specified more than once"
+        );
+    }
+
+    #[test]
+    fn test_expand_values() {
+        // thresholds and other values starting with @ are never splatted
+        let args = vec!["-Warning", "@10:20", "-Critical", "@C:\\checks\\cpu.psd1"];
+        assert_eq!(expand(&args, None).unwrap(), args);
+        let cmdargs = CommandArguments::try_from(expand(&args, None).unwrap().as_slice()).unwrap();
+        assert_eq!(
+            serde_json::to_string(&cmdargs).unwrap(),
+            r#"{"Warning":"@10:20","Critical":"@C:\\checks\\cpu.psd1"}"#
+        );
+        let args = vec!["@notes.txt", "@10:20"];
+        assert_eq!(expand(&args, None).unwrap(), args);
+
+        // switches take no value, so what follows them can be splatted, if the schema tells
+        let file = TempFile::new("switch.json", r#"{"Warning": 80}"#);
+        let splat = format!("@{}", file.path());
+        let args = vec!["-NoPerfData", splat.as_str()];
+        assert_eq!(expand(&args, None).unwrap(), args);
+        let schema: CommandSchema =
+            serde_json::from_str(r#"{"parameters": {"NoPerfData": {"type": "switch"}}}"#).unwrap();
+        assert_eq!(
+            expand(&args, Some(&schema)).unwrap(),
+            vec!["-Warning", "80", "-NoPerfData"]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use crate::icinga::ExitCode;
+use crate::rule::{compile, RuleError};
+use regex::Regex;
+
+// Name of the state of a check that wasn't executed by the daemon.
+const NOT_EXECUTED: &str = "not-executed";
+
+const RULE_KIND: &str = "state";
+
+// The state as reported by the daemon, a raw exit code or None if the check wasn't executed.
+pub type RawState = Option<i32>;
+
+fn describe(state: RawState) -> String {
+    match state {
+        Some(code) if (0..=3).contains(&code) => ExitCode::from_i32(code).to_string(),
+        Some(code) => format!("exit code {}", code),
+        None => String::from("NOT EXECUTED"),
+    }
+}
+
+#[derive(Debug)]
+struct StateMapping {
+    rule: String,
+    from: RawState,
+    to: ExitCode,
+}
+
+#[derive(Debug)]
+struct Escalation {
+    rule: String,
+    pattern: Regex,
+    to: ExitCode,
+}
+
+// Adjusts the state of a check result. Mappings like warning=ok replace a raw state, the first
+// matching one applies. Escalations like PATTERN=critical raise the state to at least the given
+// one if the check output matches.
+#[derive(Debug, Default)]
+pub struct StateRules {
+    mappings: Vec<StateMapping>,
+    escalations: Vec<Escalation>,
+}
+
+impl StateRules {
+    pub fn new<T: AsRef<str>>(mappings: &[T], escalations: &[T]) -> Result<Self, RuleError> {
+        let parse_state = |rule: &str, state: &str| {
+            ExitCode::parse(state).ok_or_else(|| {
+                RuleError::new(
+                    RULE_KIND,
+                    rule,
+                    &format!(
+                        "unknown state '{}', expected ok, warning, critical or unknown",
+                        state
+                    ),
+                )
+            })
+        };
+        let mut rules = StateRules::default();
+        for rule in mappings {
+            let rule = rule.as_ref();
+            let (from, to) = rule
+                .split_once('=')
+                .ok_or_else(|| RuleError::new(RULE_KIND, rule, "expected FROM=TO"))?;
+            let from = if from.eq_ignore_ascii_case(NOT_EXECUTED) {
+                None
+            } else if let Ok(code) = from.parse::<i32>() {
+                Some(code)
+            } else {
+                Some(parse_state(rule, from)? as i32)
+            };
+            rules.mappings.push(StateMapping {
+                rule: rule.to_owned(),
+                from,
+                to: parse_state(rule, to)?,
+            });
+        }
+        for rule in escalations {
+            let rule = rule.as_ref();
+            let (pattern, to) = rule
+                .rsplit_once('=')
+                .ok_or_else(|| RuleError::new(RULE_KIND, rule, "expected PATTERN=STATE"))?;
+            rules.escalations.push(Escalation {
+                rule: rule.to_owned(),
+                pattern: compile(RULE_KIND, rule, pattern)?,
+                to: parse_state(rule, to)?,
+            });
+        }
+        Ok(rules)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty() && self.escalations.is_empty()
+    }
+
+    // Returns the resulting state and a note for every rule that changed it.
+    pub fn apply(&self, state: RawState, output: &str) -> (ExitCode, Vec<String>) {
+        let mut notes = Vec::new();
+        let mut exitcode = match state {
+            Some(code) => ExitCode::from_i32(code),
+            None => ExitCode::Unknown,
+        };
+        if let Some(mapping) = self.mappings.iter().find(|mapping| mapping.from == state) {
+            if mapping.to != exitcode || state != Some(exitcode as i32) {
+                notes.push(format!(
+                    "state changed from {} to {} by rule '{}'",
+                    describe(state),
+                    mapping.to,
+                    mapping.rule
+                ));
+            }
+            exitcode = mapping.to;
+        }
+        for escalation in &self.escalations {
+            if escalation.to as i32 > exitcode as i32 && escalation.pattern.is_match(output) {
+                notes.push(format!(
+                    "state changed from {} to {} by rule '{}'",
+                    exitcode, escalation.to, escalation.rule
+                ));
+                exitcode = escalation.to;
+            }
+        }
+        (exitcode, notes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StateRules;
+    use crate::icinga::ExitCode;
+
+    #[test]
+    fn test_mappings() {
+        let rules =
+            StateRules::new(&["warning=ok", "not-executed=critical", "4=unknown"], &[]).unwrap();
+        assert_eq!(
+            rules.apply(Some(1), ""),
+            (
+                ExitCode::Ok,
+                vec![String::from(
+                    "state changed from WARNING to OK by rule 'warning=ok'"
+                )]
+            )
+        );
+        assert_eq!(rules.apply(Some(2), ""), (ExitCode::Critical, vec![]));
+        assert_eq!(
+            rules.apply(None, "").1,
+            vec!["state changed from NOT EXECUTED to CRITICAL by rule 'not-executed=critical'"]
+        );
+        // the default mapping of unexpected exit codes, but with a note
+        assert_eq!(
+            rules.apply(Some(4), ""),
+            (
+                ExitCode::Unknown,
+                vec![String::from(
+                    "state changed from exit code 4 to UNKNOWN by rule '4=unknown'"
+                )]
+            )
+        );
+        assert_eq!(rules.apply(Some(-1), ""), (ExitCode::Unknown, vec![]));
+    }
+
+    #[test]
+    fn test_escalations() {
+        let rules = StateRules::new(
+            &["Warning=OK"],
+            &["(?i)service .* stopped=critical", "deprecated=warning"],
+        )
+        .unwrap();
+        assert_eq!(
+            rules.apply(Some(1), "[WARNING] Service wuauserv: Stopped"),
+            (
+                ExitCode::Critical,
+                vec![
+                    String::from("state changed from WARNING to OK by rule 'Warning=OK'"),
+                    String::from(
+                        "state changed from OK to CRITICAL by rule '(?i)service .* stopped=critical'"
+                    ),
+                ]
+            )
+        );
+        // escalations never lower the state
+        assert_eq!(
+            rules.apply(Some(2), "deprecated"),
+            (ExitCode::Critical, vec![])
+        );
+    }
+
+    #[test]
+    fn test_rule_errors() {
+        let error = |mappings: &[&str], escalations: &[&str]| {
+            StateRules::new(mappings, escalations)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error(&["warning"], &[]),
+            "invalid state rule 'warning' (expected FROM=TO)"
+        );
+        assert_eq!(
+            error(&["warning=fine"], &[]),
+            "invalid state rule 'warning=fine' (unknown state 'fine', expected ok, warning, critical or unknown)"
+        );
+        assert_eq!(
+            error(&[], &["stopped"]),
+            "invalid state rule 'stopped' (expected PATTERN=STATE)"
+        );
+        assert_eq!(
+            error(&[], &["(=critical"]),
+            "invalid state rule '(=critical' (error: unclosed group)"
+        );
+    }
+}
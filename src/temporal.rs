@@ -0,0 +1,276 @@
+// Recognizers for DateTime and TimeSpan arguments. Both normalize to text that PowerShell
+// parses the same regardless of the server locale: ISO 8601 for DateTime and the constant
+// ("c") format [-][d.]hh:mm:ss[.fffffff] for TimeSpan.
+
+const TICKS_PER_MILLISECOND: u64 = 10_000;
+const TICKS_PER_SECOND: u64 = 1000 * TICKS_PER_MILLISECOND;
+const TICKS_PER_MINUTE: u64 = 60 * TICKS_PER_SECOND;
+const TICKS_PER_HOUR: u64 = 60 * TICKS_PER_MINUTE;
+const TICKS_PER_DAY: u64 = 24 * TICKS_PER_HOUR;
+
+// Months and years have no fixed length, so like TimeSpan they aren't supported as suffixes.
+const TIMESPAN_SUFFIXES: [(&str, u64); 6] = [
+    ("ms", TICKS_PER_MILLISECOND),
+    ("s", TICKS_PER_SECOND),
+    ("m", TICKS_PER_MINUTE),
+    ("h", TICKS_PER_HOUR),
+    ("d", TICKS_PER_DAY),
+    ("w", 7 * TICKS_PER_DAY),
+];
+
+// Parses a fixed number of ASCII digits within the given range.
+fn digits(text: &str, len: usize, min: u32, max: u32) -> Option<u32> {
+    if text.len() != len || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let value = text.parse().ok()?;
+    if (min..=max).contains(&value) {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Up to 7 digits, the resolution of DateTime and TimeSpan ticks.
+fn fraction(text: &str) -> Option<&str> {
+    if (1..=7).contains(&text.len()) && text.bytes().all(|b| b.is_ascii_digit()) {
+        Some(text)
+    } else {
+        None
+    }
+}
+
+// Accepts yyyy-MM-dd, optionally followed by T or a space and HH:mm[:ss[.fffffff]], and
+// optionally followed by Z or an offset ±HH:mm. Returns yyyy-MM-ddTHH:mm:ss[.fffffff][offset].
+pub fn normalize_datetime(text: &str) -> Option<String> {
+    let text = text.trim();
+    let (date, time) = match text.find(['T', 't', ' ']) {
+        Some(position) => (&text[..position], Some(&text[position + 1..])),
+        None => (text, None),
+    };
+    let mut date_parts = date.split('-');
+    let year = digits(date_parts.next()?, 4, 1, 9999)?;
+    let month = digits(date_parts.next()?, 2, 1, 12)?;
+    let day = digits(date_parts.next()?, 2, 1, days_in_month(year, month))?;
+    if date_parts.next().is_some() {
+        return None;
+    }
+    let mut normalized = format!("{:04}-{:02}-{:02}T", year, month, day);
+    let time = match time {
+        Some(time) => time.trim_start(),
+        None => {
+            normalized.push_str("00:00:00");
+            return Some(normalized);
+        }
+    };
+    let (time, offset) = if let Some(time) = time.strip_suffix(['Z', 'z']) {
+        (time, Some(String::from("Z")))
+    } else if let Some(position) = time.rfind(['+', '-']) {
+        let offset = &time[position + 1..];
+        let (hours, minutes) = match offset.split_once(':') {
+            Some((hours, minutes)) => (hours, minutes),
+            None if offset.len() == 4 => offset.split_at(2),
+            None => (offset, "00"),
+        };
+        let hours = digits(hours, 2, 0, 14)?;
+        let minutes = digits(minutes, 2, 0, 59)?;
+        let sign = &time[position..position + 1];
+        (
+            &time[..position],
+            Some(format!("{}{:02}:{:02}", sign, hours, minutes)),
+        )
+    } else {
+        (time, None)
+    };
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction_digits)) => (time, Some(fraction(fraction_digits)?)),
+        None => (time, None),
+    };
+    let mut time_parts = time.split(':');
+    let hours = digits(time_parts.next()?, 2, 0, 23)?;
+    let minutes = digits(time_parts.next()?, 2, 0, 59)?;
+    let seconds = match time_parts.next() {
+        Some(seconds) => digits(seconds, 2, 0, 59)?,
+        None if fraction.is_none() => 0,
+        None => return None,
+    };
+    if time_parts.next().is_some() {
+        return None;
+    }
+    normalized.push_str(&format!("{:02}:{:02}:{:02}", hours, minutes, seconds));
+    if let Some(fraction) = fraction {
+        normalized.push('.');
+        normalized.push_str(fraction);
+    }
+    if let Some(offset) = offset {
+        normalized.push_str(&offset);
+    }
+    Some(normalized)
+}
+
+// Accepts the TimeSpan formats [-]d, [-][d.]hh:mm[:ss[.fffffff]] and a number with one of the
+// suffixes ms, s, m, h, d or w like 1.5h. Returns the constant format [-][d.]hh:mm:ss[.fffffff].
+pub fn normalize_timespan(text: &str) -> Option<String> {
+    let text = text.trim();
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, text),
+    };
+    let ticks = if unsigned.contains(':') {
+        parse_clock(unsigned)?
+    } else if let Some(days) = digits(unsigned, unsigned.len(), 0, 10_675_199) {
+        // a plain integer is a number of days, like TimeSpan.Parse
+        u64::from(days) * TICKS_PER_DAY
+    } else {
+        parse_suffixed(unsigned)?
+    };
+    Some(format_timespan(negative && ticks > 0, ticks))
+}
+
+fn parse_clock(text: &str) -> Option<u64> {
+    let (days, clock) = match text.split_once(':') {
+        Some((head, _)) => match head.split_once('.') {
+            Some((days, _)) => (
+                Some(digits(days, days.len(), 0, 10_675_199)?),
+                &text[days.len() + 1..],
+            ),
+            None => (None, text),
+        },
+        None => return None,
+    };
+    let (clock, fraction) = match clock.split_once('.') {
+        Some((clock, fraction_digits)) => (clock, Some(fraction(fraction_digits)?)),
+        None => (clock, None),
+    };
+    let mut clock_parts = clock.split(':');
+    let hours = clock_parts.next()?;
+    let hours = digits(hours, hours.len().clamp(1, 2), 0, 23)?;
+    let minutes = digits(clock_parts.next()?, 2, 0, 59)?;
+    let seconds = match clock_parts.next() {
+        Some(seconds) => digits(seconds, 2, 0, 59)?,
+        None if fraction.is_none() => 0,
+        None => return None,
+    };
+    if clock_parts.next().is_some() {
+        return None;
+    }
+    let fraction_ticks = match fraction {
+        Some(fraction) => format!("{:0<7}", fraction).parse::<u64>().ok()?,
+        None => 0,
+    };
+    Some(
+        u64::from(days.unwrap_or(0)) * TICKS_PER_DAY
+            + u64::from(hours) * TICKS_PER_HOUR
+            + u64::from(minutes) * TICKS_PER_MINUTE
+            + u64::from(seconds) * TICKS_PER_SECOND
+            + fraction_ticks,
+    )
+}
+
+fn parse_suffixed(text: &str) -> Option<u64> {
+    // ms before m and s, so the longest suffix wins
+    let (number, ticks_per_unit) = TIMESPAN_SUFFIXES.iter().find_map(|(suffix, ticks)| {
+        text.strip_suffix(suffix)
+            .filter(|number| !number.ends_with('m'))
+            .map(|number| (number, *ticks))
+    })?;
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        return None;
+    }
+    let ticks = (number.parse::<f64>().ok()? * ticks_per_unit as f64).round();
+    if ticks.is_finite() && ticks < (i64::MAX as f64) {
+        Some(ticks as u64)
+    } else {
+        None
+    }
+}
+
+fn format_timespan(negative: bool, ticks: u64) -> String {
+    let mut formatted = String::new();
+    if negative {
+        formatted.push('-');
+    }
+    let days = ticks / TICKS_PER_DAY;
+    if days > 0 {
+        formatted.push_str(&format!("{}.", days));
+    }
+    formatted.push_str(&format!(
+        "{:02}:{:02}:{:02}",
+        ticks % TICKS_PER_DAY / TICKS_PER_HOUR,
+        ticks % TICKS_PER_HOUR / TICKS_PER_MINUTE,
+        ticks % TICKS_PER_MINUTE / TICKS_PER_SECOND
+    ));
+    let fraction_ticks = ticks % TICKS_PER_SECOND;
+    if fraction_ticks > 0 {
+        formatted.push_str(&format!(".{:07}", fraction_ticks));
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_datetime, normalize_timespan};
+
+    #[test]
+    fn test_datetime() {
+        let normalize = |text| normalize_datetime(text);
+        assert_eq!(normalize("2026-10-01").unwrap(), "2026-10-01T00:00:00");
+        assert_eq!(
+            normalize("2026-10-01 00:00").unwrap(),
+            "2026-10-01T00:00:00"
+        );
+        assert_eq!(
+            normalize("2026-10-01T13:45:30.25Z").unwrap(),
+            "2026-10-01T13:45:30.25Z"
+        );
+        assert_eq!(
+            normalize("2026-10-01 13:45+0200").unwrap(),
+            "2026-10-01T13:45:00+02:00"
+        );
+        assert_eq!(
+            normalize("2026-10-01t13:45:30-05:30").unwrap(),
+            "2026-10-01T13:45:30-05:30"
+        );
+        assert_eq!(normalize("2024-02-29").unwrap(), "2024-02-29T00:00:00");
+        assert_eq!(normalize("2026-02-29"), None);
+        assert_eq!(normalize("01.10.2026"), None);
+        assert_eq!(normalize("10/01/2026 00:00"), None);
+        assert_eq!(normalize("2026-10-01 24:00"), None);
+        assert_eq!(normalize("2026-10-01 12:00.5"), None);
+        assert_eq!(normalize("2026-10-01T12:00:00.12345678"), None);
+    }
+
+    #[test]
+    fn test_timespan() {
+        let normalize = |text| normalize_timespan(text);
+        assert_eq!(normalize("01:00:00").unwrap(), "01:00:00");
+        assert_eq!(normalize("1:30").unwrap(), "01:30:00");
+        assert_eq!(normalize("2.03:04:05.5").unwrap(), "2.03:04:05.5000000");
+        assert_eq!(normalize("-00:00:01").unwrap(), "-00:00:01");
+        assert_eq!(normalize("3").unwrap(), "3.00:00:00");
+        assert_eq!(normalize("1d").unwrap(), "1.00:00:00");
+        assert_eq!(normalize("1.5h").unwrap(), "01:30:00");
+        assert_eq!(normalize("90m").unwrap(), "01:30:00");
+        assert_eq!(normalize("20s").unwrap(), "00:00:20");
+        assert_eq!(normalize("250ms").unwrap(), "00:00:00.2500000");
+        assert_eq!(normalize("2w").unwrap(), "14.00:00:00");
+        assert_eq!(normalize("-0s").unwrap(), "00:00:00");
+        assert_eq!(normalize("1M"), None);
+        assert_eq!(normalize("1mm"), None);
+        assert_eq!(normalize("h"), None);
+        assert_eq!(normalize("25:00:00"), None);
+        assert_eq!(normalize("01:60"), None);
+        assert_eq!(normalize("1 day"), None);
+    }
+}
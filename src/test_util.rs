@@ -0,0 +1,33 @@
+// Helpers shared by the unit tests of several modules.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEMP_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// A file in the temp dir, removed when dropped. Names are unique per process and file, so
+// tests running in parallel never share one. The given name comes last to keep its extension.
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    pub fn new(name: &str, content: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "i4w_callapi_{}_{}_{}",
+            std::process::id(),
+            TEMP_FILE_COUNT.fetch_add(1, Ordering::Relaxed),
+            name
+        ));
+        std::fs::write(&path, content).unwrap();
+        TempFile(path)
+    }
+
+    pub fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}